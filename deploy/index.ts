@@ -31,6 +31,7 @@ describe("anchor-escrow", () => {
 
   // Determined Seeds
   const adminSeed = "admin";
+  const multisigSeed = "multisig";
   const stateSeed = "state";
 
   const adminKey = PublicKey.findProgramAddressSync(
@@ -38,14 +39,20 @@ describe("anchor-escrow", () => {
     program.programId
   )[0];
 
+  const multisigKey = PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode(multisigSeed))],
+    program.programId
+  )[0];
+
   it("init admin address", async () => {
     await program.methods
-      .initAdmin()
+      .initAdmin([wallet.publicKey], 1)
       .accounts({
         admin1: wallet.publicKey.toString(),
         admin2: new PublicKey("BddjKVEuSUbmAv7cyXKyzBUQDUHshwihWmkoqwXmpwvi"),
         resolver: new PublicKey("4b2mrvjxPjwzASUXYDNVhuy8bbp5jVZC2TJms1veYRJf"),
         adminState: adminKey.toString(),
+        multisig: multisigKey.toString(),
         systemProgram: anchor.web3.SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         tokenProgram: TOKEN_PROGRAM_ID,
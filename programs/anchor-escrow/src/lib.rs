@@ -3,78 +3,231 @@ use anchor_spl::token::{
     self, spl_token::instruction::AuthorityType, CloseAccount, Mint, SetAuthority, Token,
     TokenAccount, Transfer,
 };
+use std::convert::TryFrom;
 
 declare_id!("Ha9BXm9aGDU3Vyv3D2nvF2cMM2vMo1RqUiyEeL67smpP");
 
+pub const AUTHORITY_SEED: &[u8] = b"authority";
+
+/// Upper bound on the number of milestones a single escrow can hold. About 12 milestones with
+/// due dates fit in one `initialize` transaction; longer lists go through `add_milestones`.
+pub const MAX_MILESTONES: usize = 30;
+
+/// Review window applied to new escrows until the admin configures one.
+pub const DEFAULT_REVIEW_PERIOD: i64 = 7 * 24 * 60 * 60;
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Highest total fee, in basis points, that can be charged on a single payout.
+pub const MAX_FEE_BPS: u64 = 2_500;
+
+/// admin1's cut of the admin fee until the admin configures a different split.
+pub const DEFAULT_ADMIN1_SHARE_BPS: u16 = 1_500;
+
+/// Shortest notice, in seconds, between announcing a fee change and it taking effect.
+pub const MIN_FEE_DELAY: i64 = 2 * 24 * 60 * 60;
+
+/// `AdminState::pause_flags` bits. Any multisig owner can set them; clearing needs a proposal.
+pub const PAUSE_NEW_ESCROWS: u8 = 1 << 0;
+pub const PAUSE_PAYOUTS: u8 = 1 << 1;
+pub const PAUSE_DISPUTES: u8 = 1 << 2;
+pub const PAUSE_ALL: u8 = PAUSE_NEW_ESCROWS | PAUSE_PAYOUTS | PAUSE_DISPUTES;
+
+/// Upper bound on multisig owners; approvals are tracked in a `u16` bitmap.
+pub const MAX_OWNERS: usize = 10;
+
+/// Evidence entries each party can add to a single dispute record.
+pub const MAX_EVIDENCE: usize = 16;
+
 #[program]
+#[allow(clippy::result_large_err)]
 pub mod anchor_escrow {
     use super::*;
 
-    const AUTHORITY_SEED: &[u8] = b"authority";
-
     pub fn initialize(
         ctx: Context<Initialize>,
-        random_seed: u64,
-        initializer_amount: [u64; 5],
+        milestones: Vec<MilestoneParams>,
+        expires_at: i64,
     ) -> Result<()> {
+        require!(
+            !milestones.is_empty() && milestones.len() <= MAX_MILESTONES,
+            ErrorCode::InvalidMilestoneCount
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(expires_at > now, ErrorCode::InvalidExpiry);
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_NEW_ESCROWS),
+            ErrorCode::NewEscrowsPaused
+        );
+        let total_amount = MilestoneParams::total_amount(&milestones)?;
+        require!(
+            total_amount >= ctx.accounts.mint_config.min_amount
+                && total_amount <= ctx.accounts.mint_config.max_amount,
+            ErrorCode::EscrowAmountOutOfRange
+        );
+
         ctx.accounts.escrow_state.initializer_key = *ctx.accounts.initializer.key;
         ctx.accounts.escrow_state.taker = *ctx.accounts.taker.key;
-        ctx.accounts.escrow_state.initializer_amount = initializer_amount;
-        ctx.accounts.escrow_state.random_seed = random_seed;
+        ctx.accounts.escrow_state.milestones = milestones
+            .into_iter()
+            .map(MilestoneParams::into_milestone)
+            .collect();
+        ctx.accounts.escrow_state.escrow_id = ctx.accounts.escrow_counter.next_id;
         ctx.accounts.escrow_state.mint = *ctx.accounts.mint.to_account_info().key;
-        ctx.accounts.escrow_state.dispute_status = false;
+        ctx.accounts.escrow_state.open_disputes = 0;
         ctx.accounts.escrow_state.refund_status = false;
+        ctx.accounts.escrow_state.review_period = ctx.accounts.admin_state.review_period;
+        ctx.accounts.admin_state.apply_pending_fees(now);
+        ctx.accounts.mint_config.apply_pending_fees(now);
+        ctx.accounts.escrow_state.fees = ctx
+            .accounts
+            .mint_config
+            .escrow_fees(&ctx.accounts.admin_state.fees);
+        ctx.accounts.escrow_state.expires_at = expires_at;
+        ctx.accounts.escrow_state.recovery_reason = None;
         ctx.accounts.escrow_state.bump = *ctx.bumps.get("escrow_state").unwrap();
         ctx.accounts.escrow_state.vault_bump = *ctx.bumps.get("vault").unwrap();
-        ctx.accounts.admin_state.total_amount = ctx.accounts.admin_state.total_amount
-            + initializer_amount[0]
-            + initializer_amount[1]
-            + initializer_amount[2]
-            + initializer_amount[3]
-            + initializer_amount[4];
-        ctx.accounts.admin_state.locked_amount = ctx.accounts.admin_state.locked_amount
-            + initializer_amount[0]
-            + initializer_amount[1]
-            + initializer_amount[2]
-            + initializer_amount[3]
-            + initializer_amount[4];
-        ctx.accounts.admin_state.active_escrow = ctx.accounts.admin_state.active_escrow + 1;
-
-        let (vault_authority, _vault_authority_bump) =
-            Pubkey::find_program_address(&[AUTHORITY_SEED], ctx.program_id);
+        ctx.accounts.escrow_state.authority_bump = *ctx.bumps.get("vault_authority").unwrap();
+
+        ctx.accounts.escrow_counter.next_id = ctx
+            .accounts
+            .escrow_counter
+            .next_id
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.total_deposited = mint_stats
+            .total_deposited
+            .checked_add(total_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_add(total_amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let admin_state = &mut ctx.accounts.admin_state;
+        admin_state.active_escrow = admin_state
+            .active_escrow
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
 
         token::set_authority(
             ctx.accounts.into_set_authority_context(),
             AuthorityType::AccountOwner,
-            Some(vault_authority),
+            Some(ctx.accounts.vault_authority.key()),
         )?;
 
-        token::transfer(
-            ctx.accounts.into_transfer_to_pda_context(),
-            ctx.accounts.escrow_state.initializer_amount[0]
-                + ctx.accounts.escrow_state.initializer_amount[1]
-                + ctx.accounts.escrow_state.initializer_amount[2]
-                + ctx.accounts.escrow_state.initializer_amount[3]
-                + ctx.accounts.escrow_state.initializer_amount[4],
-        )?;
+        token::transfer(ctx.accounts.into_transfer_to_pda_context(), total_amount)?;
+
+        emit!(EscrowCreated {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            mint: ctx.accounts.escrow_state.mint,
+            escrow_id: ctx.accounts.escrow_state.escrow_id,
+            amount: total_amount,
+            expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Appends milestones to an escrow nobody has started working on, for lists too long to
+    /// fit in the `initialize` transaction. Their amounts are deposited into the vault.
+    pub fn add_milestones(
+        ctx: Context<AddMilestones>,
+        milestones: Vec<MilestoneParams>,
+    ) -> Result<()> {
+        require!(
+            !milestones.is_empty()
+                && ctx.accounts.escrow_state.milestones.len() + milestones.len() <= MAX_MILESTONES,
+            ErrorCode::InvalidMilestoneCount
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now < ctx.accounts.escrow_state.expires_at,
+            ErrorCode::EscrowExpired
+        );
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_NEW_ESCROWS),
+            ErrorCode::NewEscrowsPaused
+        );
+        require!(
+            !ctx.accounts.escrow_state.is_started(),
+            ErrorCode::EscrowStarted
+        );
+        let added_amount = MilestoneParams::total_amount(&milestones)?;
+        let total_amount = ctx
+            .accounts
+            .escrow_state
+            .remaining_amount()?
+            .checked_add(added_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(
+            total_amount <= ctx.accounts.mint_config.max_amount,
+            ErrorCode::EscrowAmountOutOfRange
+        );
+
+        ctx.accounts
+            .escrow_state
+            .milestones
+            .extend(milestones.into_iter().map(MilestoneParams::into_milestone));
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.total_deposited = mint_stats
+            .total_deposited
+            .checked_add(added_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_add(added_amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        token::transfer(ctx.accounts.into_transfer_to_vault_context(), added_amount)?;
+
+        emit!(MilestonesAdded {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            milestone_count: ctx.accounts.escrow_state.milestones.len() as u64,
+            amount: added_amount,
+            total_amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn init_counter(ctx: Context<InitCounter>) -> Result<()> {
+        ctx.accounts.escrow_counter.initializer = *ctx.accounts.initializer.key;
+        ctx.accounts.escrow_counter.next_id = 0;
+        ctx.accounts.escrow_counter.bump = *ctx.bumps.get("escrow_counter").unwrap();
 
         Ok(())
     }
 
-    pub fn withdraw_for_resolve(ctx: Context<WithdrawForResolve>) -> Result<()> {
-        let (_vault_authority, vault_authority_bump) =
-            Pubkey::find_program_address(&[AUTHORITY_SEED], ctx.program_id);
-        let authority_seeds = &[&AUTHORITY_SEED[..], &[vault_authority_bump]];
+    /// Moves everything left in a disputed escrow, or any escrow while payouts are paused,
+    /// to one of its parties or the recovery address. The escrow state is kept as the audit record.
+    pub fn emergency_withdraw(ctx: Context<EmergencyWithdraw>, reason_code: u16) -> Result<()> {
+        let escrow_key = ctx.accounts.escrow_state.key();
+        let authority_bump = ctx.accounts.escrow_state.authority_bump;
+        let authority_seeds = &[AUTHORITY_SEED, escrow_key.as_ref(), &[authority_bump]];
+        let remaining_amount = ctx.accounts.escrow_state.remaining_amount()?;
+        let action = AdminAction::EmergencyWithdraw {
+            escrow_state: escrow_key,
+            recipient_token_account: ctx.accounts.recipient_token_account.key(),
+            reason_code,
+        };
+        ctx.accounts
+            .proposal
+            .execute(&ctx.accounts.multisig, &action)?;
 
+        let vault_amount = ctx.accounts.vault.amount;
         token::transfer(
             ctx.accounts
-                .into_transfer_to_resolver_context()
+                .into_transfer_to_recipient_context()
                 .with_signer(&[&authority_seeds[..]]),
-            ctx.accounts.escrow_state.initializer_amount[0]
-                + ctx.accounts.escrow_state.initializer_amount[1]
-                + ctx.accounts.escrow_state.initializer_amount[2]
-                + ctx.accounts.escrow_state.initializer_amount[3]
-                + ctx.accounts.escrow_state.initializer_amount[4],
+            vault_amount,
         )?;
 
         token::close_account(
@@ -83,220 +236,1216 @@ pub mod anchor_escrow {
                 .with_signer(&[&authority_seeds[..]]),
         )?;
 
+        let mut disputed_amount: u64 = 0;
+        for milestone in ctx.accounts.escrow_state.milestones.iter_mut() {
+            if milestone.status == MilestoneStatus::Disputed {
+                disputed_amount = disputed_amount
+                    .checked_add(milestone.amount)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            if milestone.status.is_open() {
+                milestone.transition(MilestoneStatus::Recovered)?;
+            }
+        }
+        ctx.accounts.escrow_state.open_disputes = 0;
+        ctx.accounts.escrow_state.recovery_reason = Some(reason_code);
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_sub(remaining_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.recovered = mint_stats
+            .recovered
+            .checked_add(remaining_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.disputed_amount = mint_stats
+            .disputed_amount
+            .checked_sub(disputed_amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let admin_state = &mut ctx.accounts.admin_state;
+        admin_state.active_escrow = admin_state
+            .active_escrow
+            .checked_sub(1)
+            .ok_or(ErrorCode::Overflow)?;
+        admin_state.recovered_escrow = admin_state
+            .recovered_escrow
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit!(EmergencyWithdrawn {
+            escrow: escrow_key,
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            recipient_token_account: ctx.accounts.recipient_token_account.key(),
+            amount: remaining_amount,
+            recovered_amount: vault_amount,
+            reason_code,
+        });
+
         Ok(())
     }
 
     pub fn refund(ctx: Context<Refund>) -> Result<()> {
-        let (_vault_authority, vault_authority_bump) =
-            Pubkey::find_program_address(&[AUTHORITY_SEED], ctx.program_id);
-        let authority_seeds = &[&AUTHORITY_SEED[..], &[vault_authority_bump]];
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_PAYOUTS),
+            ErrorCode::PayoutsPaused
+        );
+        let escrow_key = ctx.accounts.escrow_state.key();
+        let authority_bump = ctx.accounts.escrow_state.authority_bump;
+        let authority_seeds = &[AUTHORITY_SEED, escrow_key.as_ref(), &[authority_bump]];
+        // disputed milestones stay in the vault until the resolver rules on them
+        let mut refund_amount: u64 = 0;
+        for milestone in ctx.accounts.escrow_state.milestones.iter_mut() {
+            if milestone.status.is_open() && milestone.status != MilestoneStatus::Disputed {
+                milestone.transition(MilestoneStatus::Refunded)?;
+                refund_amount = refund_amount
+                    .checked_add(milestone.amount)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+        }
+        require!(refund_amount > 0, ErrorCode::NothingToRefund);
+        let fees = ctx.accounts.escrow_state.fees;
+        let fee_split = fees.split(refund_amount, fees.admin_fee_bps, 0)?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_initializer_context()
                 .with_signer(&[&authority_seeds[..]]),
-            (ctx.accounts.escrow_state.initializer_amount[0]
-                + ctx.accounts.escrow_state.initializer_amount[1]
-                + ctx.accounts.escrow_state.initializer_amount[2]
-                + ctx.accounts.escrow_state.initializer_amount[3]
-                + ctx.accounts.escrow_state.initializer_amount[4])
-                * (100 - ctx.accounts.admin_state.admin_fee)
-                / 100,
+            fee_split.recipient_amount,
         )?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_admin1_context()
                 .with_signer(&[&authority_seeds[..]]),
-            (ctx.accounts.escrow_state.initializer_amount[0]
-                + ctx.accounts.escrow_state.initializer_amount[1]
-                + ctx.accounts.escrow_state.initializer_amount[2]
-                + ctx.accounts.escrow_state.initializer_amount[3]
-                + ctx.accounts.escrow_state.initializer_amount[4])
-                * ctx.accounts.admin_state.admin_fee
-                * 15
-                / 10000,
+            fee_split.admin1_fee,
         )?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_admin2_context()
                 .with_signer(&[&authority_seeds[..]]),
-            (ctx.accounts.escrow_state.initializer_amount[0]
-                + ctx.accounts.escrow_state.initializer_amount[1]
-                + ctx.accounts.escrow_state.initializer_amount[2]
-                + ctx.accounts.escrow_state.initializer_amount[3]
-                + ctx.accounts.escrow_state.initializer_amount[4])
-                * ctx.accounts.admin_state.admin_fee
-                * 85
-                / 10000,
+            fee_split.admin2_fee,
         )?;
 
-        ctx.accounts.admin_state.locked_amount = ctx.accounts.admin_state.locked_amount
-            - ctx.accounts.escrow_state.initializer_amount[0]
-            - ctx.accounts.escrow_state.initializer_amount[1]
-            - ctx.accounts.escrow_state.initializer_amount[2]
-            - ctx.accounts.escrow_state.initializer_amount[3]
-            - ctx.accounts.escrow_state.initializer_amount[4];
-        ctx.accounts.admin_state.refunded_escrow = ctx.accounts.admin_state.refunded_escrow + 1;
-        ctx.accounts.admin_state.active_escrow = ctx.accounts.admin_state.active_escrow - 1;
-        ctx.accounts.admin_state.completed_escrow = ctx.accounts.admin_state.completed_escrow + 1;
-        ctx.accounts.escrow_state.initializer_amount = [0, 0, 0, 0, 0];
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_sub(refund_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.refunded = mint_stats
+            .refunded
+            .checked_add(fee_split.recipient_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.fees_collected = mint_stats
+            .fees_collected
+            .checked_add(fee_split.total_fees()?)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let settled = ctx.accounts.escrow_state.is_settled();
+        let admin_state = &mut ctx.accounts.admin_state;
+        if settled {
+            admin_state.active_escrow = admin_state
+                .active_escrow
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
+            admin_state.completed_escrow = admin_state
+                .completed_escrow
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+            admin_state.refunded_escrow = admin_state
+                .refunded_escrow
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
         ctx.accounts.escrow_state.refund_status = true;
 
+        emit!(Refunded {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            amount: refund_amount,
+            initializer_amount: fee_split.recipient_amount,
+            admin1_fee: fee_split.admin1_fee,
+            admin2_fee: fee_split.admin2_fee,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel(ctx: Context<Cancel>) -> Result<()> {
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_PAYOUTS),
+            ErrorCode::PayoutsPaused
+        );
+        let escrow_key = ctx.accounts.escrow_state.key();
+        let authority_bump = ctx.accounts.escrow_state.authority_bump;
+        let authority_seeds = &[AUTHORITY_SEED, escrow_key.as_ref(), &[authority_bump]];
+        let remaining_amount = ctx.accounts.escrow_state.remaining_amount()?;
+        let fees = ctx.accounts.escrow_state.fees;
+        let fee_split = fees.split(remaining_amount, fees.cancel_fee_bps, 0)?;
+
+        // anything left in the vault beyond the fees (e.g. stray deposits) goes back to the
+        // initializer so the vault can always be closed
+        let initializer_amount = ctx
+            .accounts
+            .vault
+            .amount
+            .checked_sub(fee_split.admin1_fee)
+            .and_then(|amount| amount.checked_sub(fee_split.admin2_fee))
+            .ok_or(ErrorCode::Overflow)?;
+        token::transfer(
+            ctx.accounts
+                .into_transfer_to_initializer_context()
+                .with_signer(&[&authority_seeds[..]]),
+            initializer_amount,
+        )?;
+
+        if fees.cancel_fee_bps > 0 {
+            token::transfer(
+                ctx.accounts
+                    .into_transfer_to_admin1_context()
+                    .with_signer(&[&authority_seeds[..]]),
+                fee_split.admin1_fee,
+            )?;
+
+            token::transfer(
+                ctx.accounts
+                    .into_transfer_to_admin2_context()
+                    .with_signer(&[&authority_seeds[..]]),
+                fee_split.admin2_fee,
+            )?;
+        }
+
+        token::close_account(
+            ctx.accounts
+                .into_close_context()
+                .with_signer(&[&authority_seeds[..]]),
+        )?;
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_sub(remaining_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.refunded = mint_stats
+            .refunded
+            .checked_add(fee_split.recipient_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.fees_collected = mint_stats
+            .fees_collected
+            .checked_add(fee_split.total_fees()?)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let settled = ctx.accounts.escrow_state.is_settled();
+        let admin_state = &mut ctx.accounts.admin_state;
+        if !settled {
+            admin_state.active_escrow = admin_state
+                .active_escrow
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
+            admin_state.cancelled_escrow = admin_state
+                .cancelled_escrow
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        emit!(EscrowCancelled {
+            escrow: escrow_key,
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            amount: remaining_amount,
+            initializer_amount,
+            admin1_fee: fee_split.admin1_fee,
+            admin2_fee: fee_split.admin2_fee,
+        });
+
+        Ok(())
+    }
+
+    pub fn reclaim_expired(ctx: Context<ReclaimExpired>) -> Result<()> {
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_PAYOUTS),
+            ErrorCode::PayoutsPaused
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= ctx.accounts.escrow_state.expires_at,
+            ErrorCode::EscrowNotExpired
+        );
+        let escrow_key = ctx.accounts.escrow_state.key();
+        let authority_bump = ctx.accounts.escrow_state.authority_bump;
+        let authority_seeds = &[AUTHORITY_SEED, escrow_key.as_ref(), &[authority_bump]];
+
+        let mut reclaim_amount: u64 = 0;
+        for milestone in ctx.accounts.escrow_state.milestones.iter_mut() {
+            if milestone.status == MilestoneStatus::Pending {
+                milestone.transition(MilestoneStatus::Refunded)?;
+                reclaim_amount = reclaim_amount
+                    .checked_add(milestone.amount)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+        }
+        require!(reclaim_amount > 0, ErrorCode::NothingToReclaim);
+
+        let fees = ctx.accounts.escrow_state.fees;
+        let admin_fee_bps = if ctx.accounts.admin_state.waive_expiry_fee {
+            0
+        } else {
+            fees.admin_fee_bps
+        };
+        let fee_split = fees.split(reclaim_amount, admin_fee_bps, 0)?;
+
+        token::transfer(
+            ctx.accounts
+                .into_transfer_to_initializer_context()
+                .with_signer(&[&authority_seeds[..]]),
+            fee_split.recipient_amount,
+        )?;
+
+        if admin_fee_bps > 0 {
+            token::transfer(
+                ctx.accounts
+                    .into_transfer_to_admin1_context()
+                    .with_signer(&[&authority_seeds[..]]),
+                fee_split.admin1_fee,
+            )?;
+
+            token::transfer(
+                ctx.accounts
+                    .into_transfer_to_admin2_context()
+                    .with_signer(&[&authority_seeds[..]]),
+                fee_split.admin2_fee,
+            )?;
+        }
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_sub(reclaim_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.refunded = mint_stats
+            .refunded
+            .checked_add(fee_split.recipient_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.fees_collected = mint_stats
+            .fees_collected
+            .checked_add(fee_split.total_fees()?)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let settled = ctx.accounts.escrow_state.is_settled();
+        let admin_state = &mut ctx.accounts.admin_state;
+        if settled {
+            admin_state.active_escrow = admin_state
+                .active_escrow
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
+            admin_state.completed_escrow = admin_state
+                .completed_escrow
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+            admin_state.refunded_escrow = admin_state
+                .refunded_escrow
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+
+            ctx.accounts.vault.reload()?;
+            if ctx.accounts.vault.amount == 0 {
+                token::close_account(
+                    ctx.accounts
+                        .into_close_context()
+                        .with_signer(&[&authority_seeds[..]]),
+                )?;
+            }
+        }
+
+        emit!(ExpiredReclaimed {
+            escrow: escrow_key,
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            amount: reclaim_amount,
+            initializer_amount: fee_split.recipient_amount,
+            admin1_fee: fee_split.admin1_fee,
+            admin2_fee: fee_split.admin2_fee,
+        });
+
+        Ok(())
+    }
+
+    pub fn submit_milestone(
+        ctx: Context<SubmitMilestone>,
+        milestone_idx: u64,
+        deliverable_hash: [u8; 32],
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now < ctx.accounts.escrow_state.expires_at,
+            ErrorCode::EscrowExpired
+        );
+        let milestone = ctx.accounts.escrow_state.milestone_mut(milestone_idx)?;
+        milestone.transition(MilestoneStatus::Submitted)?;
+        milestone.deliverable_hash = deliverable_hash;
+        milestone.submitted_at = Some(now);
+        let milestone_amount = milestone.amount;
+
+        emit!(MilestoneSubmitted {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            milestone_idx,
+            amount: milestone_amount,
+            deliverable_hash,
+        });
+
         Ok(())
     }
 
     pub fn approve(ctx: Context<Approve>, milestone_idx: u64) -> Result<()> {
-        let (_vault_authority, vault_authority_bump) =
-            Pubkey::find_program_address(&[AUTHORITY_SEED], ctx.program_id);
-        let authority_seeds = &[&AUTHORITY_SEED[..], &[vault_authority_bump]];
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_PAYOUTS),
+            ErrorCode::PayoutsPaused
+        );
+        let escrow_key = ctx.accounts.escrow_state.key();
+        let authority_bump = ctx.accounts.escrow_state.authority_bump;
+        let authority_seeds = &[AUTHORITY_SEED, escrow_key.as_ref(), &[authority_bump]];
+        let milestone = ctx.accounts.escrow_state.milestone_mut(milestone_idx)?;
+        milestone.transition(MilestoneStatus::Approved)?;
+        let milestone_amount = milestone.amount;
+        let fees = ctx.accounts.escrow_state.fees;
+        let fee_split = fees.split(milestone_amount, fees.admin_fee_bps, 0)?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_taker_context()
                 .with_signer(&[&authority_seeds[..]]),
-            ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize]
-                * (100 - ctx.accounts.admin_state.admin_fee)
-                / 100,
+            fee_split.recipient_amount,
         )?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_admin1_context()
                 .with_signer(&[&authority_seeds[..]]),
-            ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize]
-                * ctx.accounts.admin_state.admin_fee
-                * 15
-                / 10000,
+            fee_split.admin1_fee,
         )?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_admin2_context()
                 .with_signer(&[&authority_seeds[..]]),
-            ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize]
-                * ctx.accounts.admin_state.admin_fee
-                * 85
-                / 10000,
+            fee_split.admin2_fee,
+        )?;
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_sub(milestone_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.paid_to_takers = mint_stats
+            .paid_to_takers
+            .checked_add(fee_split.recipient_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.fees_collected = mint_stats
+            .fees_collected
+            .checked_add(fee_split.total_fees()?)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let settled = ctx.accounts.escrow_state.is_settled();
+        let admin_state = &mut ctx.accounts.admin_state;
+        if settled {
+            admin_state.active_escrow = admin_state
+                .active_escrow
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
+            admin_state.completed_escrow = admin_state
+                .completed_escrow
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        emit!(MilestoneApproved {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            milestone_idx,
+            amount: milestone_amount,
+            taker_amount: fee_split.recipient_amount,
+            admin1_fee: fee_split.admin1_fee,
+            admin2_fee: fee_split.admin2_fee,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_after_timeout(ctx: Context<ClaimAfterTimeout>, milestone_idx: u64) -> Result<()> {
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_PAYOUTS),
+            ErrorCode::PayoutsPaused
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let escrow_key = ctx.accounts.escrow_state.key();
+        let authority_bump = ctx.accounts.escrow_state.authority_bump;
+        let authority_seeds = &[AUTHORITY_SEED, escrow_key.as_ref(), &[authority_bump]];
+        let review_period = ctx.accounts.escrow_state.review_period;
+        let milestone = ctx.accounts.escrow_state.milestone_mut(milestone_idx)?;
+        require!(
+            now >= milestone.review_ends_at(review_period)?,
+            ErrorCode::ReviewPeriodNotElapsed
+        );
+        milestone.transition(MilestoneStatus::Approved)?;
+        let milestone_amount = milestone.amount;
+        let fees = ctx.accounts.escrow_state.fees;
+        let fee_split = fees.split(milestone_amount, fees.admin_fee_bps, 0)?;
+
+        token::transfer(
+            ctx.accounts
+                .into_transfer_to_taker_context()
+                .with_signer(&[&authority_seeds[..]]),
+            fee_split.recipient_amount,
+        )?;
+
+        token::transfer(
+            ctx.accounts
+                .into_transfer_to_admin1_context()
+                .with_signer(&[&authority_seeds[..]]),
+            fee_split.admin1_fee,
         )?;
 
-        ctx.accounts.admin_state.locked_amount = ctx.accounts.admin_state.locked_amount
-            - ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize];
+        token::transfer(
+            ctx.accounts
+                .into_transfer_to_admin2_context()
+                .with_signer(&[&authority_seeds[..]]),
+            fee_split.admin2_fee,
+        )?;
 
-        ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize] = 0;
-        if ctx.accounts.escrow_state.initializer_amount[0]
-            + ctx.accounts.escrow_state.initializer_amount[1]
-            + ctx.accounts.escrow_state.initializer_amount[2]
-            + ctx.accounts.escrow_state.initializer_amount[3]
-            + ctx.accounts.escrow_state.initializer_amount[4]
-            == 0
-        {
-            ctx.accounts.admin_state.active_escrow = ctx.accounts.admin_state.active_escrow - 1;
-            ctx.accounts.admin_state.completed_escrow =
-                ctx.accounts.admin_state.completed_escrow + 1;
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_sub(milestone_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.paid_to_takers = mint_stats
+            .paid_to_takers
+            .checked_add(fee_split.recipient_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.fees_collected = mint_stats
+            .fees_collected
+            .checked_add(fee_split.total_fees()?)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let settled = ctx.accounts.escrow_state.is_settled();
+        let admin_state = &mut ctx.accounts.admin_state;
+        if settled {
+            admin_state.active_escrow = admin_state
+                .active_escrow
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
+            admin_state.completed_escrow = admin_state
+                .completed_escrow
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
         }
 
+        emit!(MilestoneApproved {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            milestone_idx,
+            amount: milestone_amount,
+            taker_amount: fee_split.recipient_amount,
+            admin1_fee: fee_split.admin1_fee,
+            admin2_fee: fee_split.admin2_fee,
+        });
+
         Ok(())
     }
 
-    pub fn resolve(ctx: Context<Resolve>, milestone_idx: u64) -> Result<()> {
-        let (_vault_authority, vault_authority_bump) =
-            Pubkey::find_program_address(&[AUTHORITY_SEED], ctx.program_id);
-        let authority_seeds = &[&AUTHORITY_SEED[..], &[vault_authority_bump]];
+    /// Rules on a disputed milestone: after fees, `taker_share_bps` of it goes to the taker and
+    /// the rest back to the initializer.
+    pub fn resolve(ctx: Context<Resolve>, milestone_idx: u64, taker_share_bps: u16) -> Result<()> {
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_PAYOUTS),
+            ErrorCode::PayoutsPaused
+        );
+        require!(
+            taker_share_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidTakerShare
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            ctx.accounts.admin_state.can_resolve(
+                ctx.accounts.resolver.key,
+                ctx.accounts.dispute_record.opened_at,
+                now
+            ),
+            ErrorCode::Unauthorized
+        );
+        let escrow_key = ctx.accounts.escrow_state.key();
+        let authority_bump = ctx.accounts.escrow_state.authority_bump;
+        let authority_seeds = &[AUTHORITY_SEED, escrow_key.as_ref(), &[authority_bump]];
+        require!(
+            ctx.accounts.dispute_record.is_open(),
+            ErrorCode::DisputeClosed
+        );
+        let milestone = ctx.accounts.escrow_state.milestone_mut(milestone_idx)?;
+        require!(
+            milestone.status == MilestoneStatus::Disputed,
+            ErrorCode::NotDisputed
+        );
+        milestone.transition(MilestoneStatus::Resolved)?;
+        milestone.taker_share_bps = Some(taker_share_bps);
+        let milestone_amount = milestone.amount;
+        ctx.accounts.dispute_record.resolved_at = Some(now);
+        ctx.accounts.dispute_record.taker_share_bps = Some(taker_share_bps);
+        ctx.accounts.escrow_state.open_disputes = ctx
+            .accounts
+            .escrow_state
+            .open_disputes
+            .checked_sub(1)
+            .ok_or(ErrorCode::Overflow)?;
+        let fees = ctx.accounts.escrow_state.fees;
+        let fee_split = fees.split(milestone_amount, fees.admin_fee_bps, fees.resolver_fee_bps)?;
+        let taker_amount = mul_div(
+            fee_split.recipient_amount,
+            taker_share_bps as u64,
+            BPS_DENOMINATOR,
+        )?;
+        let initializer_amount = fee_split
+            .recipient_amount
+            .checked_sub(taker_amount)
+            .ok_or(ErrorCode::Overflow)?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_taker_context()
                 .with_signer(&[&authority_seeds[..]]),
-            ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize]
-                * (100
-                    - ctx.accounts.admin_state.admin_fee
-                    - ctx.accounts.admin_state.resolver_fee)
-                / 100,
+            taker_amount,
+        )?;
+
+        token::transfer(
+            ctx.accounts
+                .into_transfer_to_initializer_context()
+                .with_signer(&[&authority_seeds[..]]),
+            initializer_amount,
         )?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_admin1_context()
                 .with_signer(&[&authority_seeds[..]]),
-            ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize]
-                * ctx.accounts.admin_state.admin_fee
-                * 15
-                / 10000,
+            fee_split.admin1_fee,
         )?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_admin2_context()
                 .with_signer(&[&authority_seeds[..]]),
-            ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize]
-                * ctx.accounts.admin_state.admin_fee
-                * 85
-                / 10000,
+            fee_split.admin2_fee,
         )?;
 
         token::transfer(
             ctx.accounts
                 .into_transfer_to_resolver_context()
                 .with_signer(&[&authority_seeds[..]]),
-            ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize]
-                * ctx.accounts.admin_state.resolver_fee
-                / 100,
+            fee_split.resolver_fee,
         )?;
 
-        ctx.accounts.admin_state.locked_amount = ctx.accounts.admin_state.locked_amount
-            - ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize];
+        // The opener keeps the bond unless the ruling leaves them less than half.
+        let bond_amount = ctx.accounts.dispute_record.bond_amount;
+        let opener_is_taker = ctx.accounts.dispute_record.opener == ctx.accounts.escrow_state.taker;
+        let opener_share_bps = if opener_is_taker {
+            taker_share_bps as u64
+        } else {
+            BPS_DENOMINATOR
+                .checked_sub(taker_share_bps as u64)
+                .ok_or(ErrorCode::Overflow)?
+        };
+        let bond_forfeited =
+            opener_share_bps.checked_mul(2).ok_or(ErrorCode::Overflow)? < BPS_DENOMINATOR;
+        if bond_amount > 0 {
+            let bond_recipient = if bond_forfeited {
+                ctx.accounts.resolver_token_account.to_account_info()
+            } else if opener_is_taker {
+                ctx.accounts.taker_token_account.to_account_info()
+            } else {
+                ctx.accounts.initializer_token_account.to_account_info()
+            };
+            token::transfer(
+                ctx.accounts
+                    .into_transfer_bond_context(bond_recipient)
+                    .with_signer(&[&authority_seeds[..]]),
+                bond_amount,
+            )?;
+        }
+        token::close_account(
+            ctx.accounts
+                .into_close_bond_vault_context()
+                .with_signer(&[&authority_seeds[..]]),
+        )?;
 
-        ctx.accounts.escrow_state.initializer_amount[milestone_idx as usize] = 0;
-        if ctx.accounts.escrow_state.initializer_amount[0]
-            + ctx.accounts.escrow_state.initializer_amount[1]
-            + ctx.accounts.escrow_state.initializer_amount[2]
-            + ctx.accounts.escrow_state.initializer_amount[3]
-            + ctx.accounts.escrow_state.initializer_amount[4]
-            == 0
-        {
-            ctx.accounts.admin_state.active_escrow = ctx.accounts.admin_state.active_escrow - 1;
-            ctx.accounts.admin_state.completed_escrow =
-                ctx.accounts.admin_state.completed_escrow + 1;
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        if bond_forfeited {
+            mint_stats.forfeited_bonds = mint_stats
+                .forfeited_bonds
+                .checked_add(bond_amount)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+        mint_stats.locked_amount = mint_stats
+            .locked_amount
+            .checked_sub(milestone_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.disputed_amount = mint_stats
+            .disputed_amount
+            .checked_sub(milestone_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.paid_to_takers = mint_stats
+            .paid_to_takers
+            .checked_add(taker_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.refunded = mint_stats
+            .refunded
+            .checked_add(initializer_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        mint_stats.fees_collected = mint_stats
+            .fees_collected
+            .checked_add(fee_split.total_fees()?)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let settled = ctx.accounts.escrow_state.is_settled();
+        let admin_state = &mut ctx.accounts.admin_state;
+        match taker_share_bps as u64 {
+            BPS_DENOMINATOR => {
+                admin_state.resolved_for_taker = admin_state
+                    .resolved_for_taker
+                    .checked_add(1)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            0 => {
+                admin_state.resolved_for_initializer = admin_state
+                    .resolved_for_initializer
+                    .checked_add(1)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            _ => {
+                admin_state.resolved_split = admin_state
+                    .resolved_split
+                    .checked_add(1)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+        }
+        if settled {
+            admin_state.active_escrow = admin_state
+                .active_escrow
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
+            admin_state.completed_escrow = admin_state
+                .completed_escrow
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
         }
 
+        emit!(DisputeResolved {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            milestone_idx,
+            dispute_record: ctx.accounts.dispute_record.key(),
+            taker_share_bps,
+            amount: milestone_amount,
+            taker_amount,
+            initializer_amount,
+            admin1_fee: fee_split.admin1_fee,
+            admin2_fee: fee_split.admin2_fee,
+            resolver_fee: fee_split.resolver_fee,
+            bond_amount,
+            bond_forfeited,
+            resolved_by: *ctx.accounts.resolver.key,
+        });
+
         Ok(())
     }
 
-    pub fn init_admin(ctx: Context<InitAdmin>) -> Result<()> {
+    pub fn init_admin(ctx: Context<InitAdmin>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        Multisig::validate(&owners, threshold)?;
+        ctx.accounts.multisig.owners = owners;
+        ctx.accounts.multisig.threshold = threshold;
+        ctx.accounts.multisig.owner_set_seqno = 0;
+        ctx.accounts.multisig.proposal_count = 0;
+        ctx.accounts.multisig.bump = *ctx.bumps.get("multisig").unwrap();
+
         ctx.accounts.admin_state.admin1 = *ctx.accounts.admin1.key;
         ctx.accounts.admin_state.admin2 = *ctx.accounts.admin2.key;
         ctx.accounts.admin_state.resolver = *ctx.accounts.resolver.key;
         ctx.accounts.admin_state.bump = *ctx.bumps.get("admin_state").unwrap();
-        ctx.accounts.admin_state.resolver_fee = 0;
-        ctx.accounts.admin_state.admin_fee = 0;
-        ctx.accounts.admin_state.total_amount = 0;
-        ctx.accounts.admin_state.locked_amount = 0;
+        ctx.accounts.admin_state.fees = FeeSchedule {
+            admin_fee_bps: 0,
+            resolver_fee_bps: 0,
+            cancel_fee_bps: 0,
+            admin1_share_bps: DEFAULT_ADMIN1_SHARE_BPS,
+        };
         ctx.accounts.admin_state.active_escrow = 0;
         ctx.accounts.admin_state.completed_escrow = 0;
         ctx.accounts.admin_state.disputed_escrow = 0;
+        ctx.accounts.admin_state.resolved_for_taker = 0;
+        ctx.accounts.admin_state.resolved_for_initializer = 0;
+        ctx.accounts.admin_state.resolved_split = 0;
         ctx.accounts.admin_state.refunded_escrow = 0;
+        ctx.accounts.admin_state.review_period = DEFAULT_REVIEW_PERIOD;
+        ctx.accounts.admin_state.waive_expiry_fee = false;
+        ctx.accounts.admin_state.cancelled_escrow = 0;
+        ctx.accounts.admin_state.pause_flags = 0;
+        ctx.accounts.admin_state.recovery_address = Pubkey::default();
+        ctx.accounts.admin_state.recovered_escrow = 0;
+        ctx.accounts.admin_state.pending_admin1 = None;
+        ctx.accounts.admin_state.pending_admin2 = None;
+        ctx.accounts.admin_state.pending_resolver = None;
+        ctx.accounts.admin_state.pending_fees = None;
+        ctx.accounts.admin_state.fees_effective_at = 0;
+        ctx.accounts.admin_state.backup_resolver = Pubkey::default();
+        ctx.accounts.admin_state.resolver_timeout = 0;
+        ctx.accounts.admin_state.withdrawn_dispute = 0;
+        Ok(())
+    }
+
+    /// Stages new role keys. Each staged key only takes over once it signs `accept_role`.
+    pub fn propose_admin(ctx: Context<ProposeAdmin>) -> Result<()> {
+        let action = AdminAction::ProposeAdmin {
+            admin1: *ctx.accounts.new_admin1.key,
+            admin2: *ctx.accounts.new_admin2.key,
+            resolver: *ctx.accounts.new_resolver.key,
+        };
+        ctx.accounts
+            .proposal
+            .execute(&ctx.accounts.multisig, &action)?;
+
+        let admin_state = &mut ctx.accounts.admin_state;
+        admin_state.pending_admin1 =
+            Some(*ctx.accounts.new_admin1.key).filter(|key| *key != admin_state.admin1);
+        admin_state.pending_admin2 =
+            Some(*ctx.accounts.new_admin2.key).filter(|key| *key != admin_state.admin2);
+        admin_state.pending_resolver =
+            Some(*ctx.accounts.new_resolver.key).filter(|key| *key != admin_state.resolver);
+
+        Ok(())
+    }
+
+    pub fn accept_role(ctx: Context<AcceptRole>) -> Result<()> {
+        let accepted = ctx
+            .accounts
+            .admin_state
+            .accept_pending(*ctx.accounts.new_key.key);
+        require!(accepted, ErrorCode::NoPendingRole);
+
+        emit!(AdminChanged {
+            admin1: ctx.accounts.admin_state.admin1,
+            admin2: ctx.accounts.admin_state.admin2,
+            resolver: ctx.accounts.admin_state.resolver,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_admin_proposal(ctx: Context<SetConfig>) -> Result<()> {
+        ctx.accounts
+            .proposal
+            .execute(&ctx.accounts.multisig, &AdminAction::CancelAdminProposal)?;
+        ctx.accounts.admin_state.pending_admin1 = None;
+        ctx.accounts.admin_state.pending_admin2 = None;
+        ctx.accounts.admin_state.pending_resolver = None;
+
+        Ok(())
+    }
+
+    pub fn set_fee(
+        ctx: Context<SetFee>,
+        admin_fee_bps: u16,
+        resolver_fee_bps: u16,
+        cancel_fee_bps: u16,
+        admin1_share_bps: u16,
+        effective_at: i64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let earliest = now.checked_add(MIN_FEE_DELAY).ok_or(ErrorCode::Overflow)?;
+        require!(effective_at >= earliest, ErrorCode::FeeDelayTooShort);
+        let fees = FeeSchedule {
+            admin_fee_bps,
+            resolver_fee_bps,
+            cancel_fee_bps,
+            admin1_share_bps,
+        };
+        fees.validate()?;
+        ctx.accounts.proposal.execute(
+            &ctx.accounts.multisig,
+            &AdminAction::SetFee { fees, effective_at },
+        )?;
+
+        // a schedule that is already due takes effect before it gets replaced
+        ctx.accounts.admin_state.apply_pending_fees(now);
+        ctx.accounts.admin_state.pending_fees = Some(fees);
+        ctx.accounts.admin_state.fees_effective_at = effective_at;
+
+        emit!(FeesChanged { fees, effective_at });
+
+        Ok(())
+    }
+
+    pub fn set_review_period(ctx: Context<SetConfig>, review_period: i64) -> Result<()> {
+        require!(review_period > 0, ErrorCode::InvalidReviewPeriod);
+        ctx.accounts.proposal.execute(
+            &ctx.accounts.multisig,
+            &AdminAction::SetReviewPeriod { review_period },
+        )?;
+        ctx.accounts.admin_state.review_period = review_period;
+
+        Ok(())
+    }
+
+    pub fn set_expiry_fee_waiver(ctx: Context<SetConfig>, waive_expiry_fee: bool) -> Result<()> {
+        ctx.accounts.proposal.execute(
+            &ctx.accounts.multisig,
+            &AdminAction::SetExpiryFeeWaiver { waive_expiry_fee },
+        )?;
+        ctx.accounts.admin_state.waive_expiry_fee = waive_expiry_fee;
+
+        Ok(())
+    }
+
+    /// Circuit breaker: any single multisig owner can switch pause bits on.
+    pub fn pause(ctx: Context<Pause>, pause_flags: u8) -> Result<()> {
+        require!(
+            pause_flags != 0 && pause_flags & !PAUSE_ALL == 0,
+            ErrorCode::InvalidPauseFlags
+        );
+        ctx.accounts.multisig.owner_index(ctx.accounts.owner.key)?;
+        ctx.accounts.admin_state.pause_flags |= pause_flags;
+
+        Ok(())
+    }
+
+    /// Replaces the pause bits as a whole, which is the only way to lift a pause.
+    pub fn set_pause_flags(ctx: Context<SetConfig>, pause_flags: u8) -> Result<()> {
+        require!(pause_flags & !PAUSE_ALL == 0, ErrorCode::InvalidPauseFlags);
+        ctx.accounts.proposal.execute(
+            &ctx.accounts.multisig,
+            &AdminAction::SetPauseFlags { pause_flags },
+        )?;
+        ctx.accounts.admin_state.pause_flags = pause_flags;
+
+        Ok(())
+    }
+
+    pub fn set_recovery_address(ctx: Context<SetConfig>, recovery_address: Pubkey) -> Result<()> {
+        ctx.accounts.proposal.execute(
+            &ctx.accounts.multisig,
+            &AdminAction::SetRecoveryAddress { recovery_address },
+        )?;
+        ctx.accounts.admin_state.recovery_address = recovery_address;
+
+        Ok(())
+    }
+
+    /// Escalation path for disputes the resolver does not rule on in time.
+    pub fn set_backup_resolver(
+        ctx: Context<SetConfig>,
+        backup_resolver: Pubkey,
+        resolver_timeout: i64,
+    ) -> Result<()> {
+        require!(resolver_timeout >= 0, ErrorCode::InvalidResolverTimeout);
+        ctx.accounts.proposal.execute(
+            &ctx.accounts.multisig,
+            &AdminAction::SetBackupResolver {
+                backup_resolver,
+                resolver_timeout,
+            },
+        )?;
+        ctx.accounts.admin_state.backup_resolver = backup_resolver;
+        ctx.accounts.admin_state.resolver_timeout = resolver_timeout;
+
+        Ok(())
+    }
+
+    pub fn add_mint(ctx: Context<AddMint>, params: MintConfigParams) -> Result<()> {
+        params.validate()?;
+        let action = AdminAction::AddMint {
+            mint: ctx.accounts.mint.key(),
+            params,
+        };
+        ctx.accounts
+            .proposal
+            .execute(&ctx.accounts.multisig, &action)?;
+
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.mint = ctx.accounts.mint.key();
+        mint_config.enabled = params.enabled;
+        mint_config.fees = params.fees;
+        mint_config.min_amount = params.min_amount;
+        mint_config.max_amount = params.max_amount;
+        mint_config.dispute_bond = params.dispute_bond;
+        mint_config.pending_fees = None;
+        mint_config.fees_effective_at = 0;
+        mint_config.bump = *ctx.bumps.get("mint_config").unwrap();
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.mint = ctx.accounts.mint.key();
+        mint_stats.total_deposited = 0;
+        mint_stats.locked_amount = 0;
+        mint_stats.paid_to_takers = 0;
+        mint_stats.refunded = 0;
+        mint_stats.fees_collected = 0;
+        mint_stats.recovered = 0;
+        mint_stats.disputed_amount = 0;
+        mint_stats.forfeited_bonds = 0;
+        mint_stats.bump = *ctx.bumps.get("mint_stats").unwrap();
+
+        Ok(())
+    }
+
+    /// Flag and limit changes apply at once; a fee change is queued like `set_fee`.
+    pub fn update_mint_config(
+        ctx: Context<UpdateMintConfig>,
+        params: MintConfigParams,
+        fees_effective_at: i64,
+    ) -> Result<()> {
+        params.validate()?;
+        let action = AdminAction::UpdateMintConfig {
+            mint: ctx.accounts.mint_config.mint,
+            params,
+            fees_effective_at,
+        };
+        ctx.accounts
+            .proposal
+            .execute(&ctx.accounts.multisig, &action)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let mint_config = &mut ctx.accounts.mint_config;
+        mint_config.apply_pending_fees(now);
+        mint_config.enabled = params.enabled;
+        mint_config.min_amount = params.min_amount;
+        mint_config.max_amount = params.max_amount;
+        mint_config.dispute_bond = params.dispute_bond;
+        if params.fees == mint_config.fees {
+            mint_config.pending_fees = None;
+        } else {
+            let earliest = now.checked_add(MIN_FEE_DELAY).ok_or(ErrorCode::Overflow)?;
+            require!(fees_effective_at >= earliest, ErrorCode::FeeDelayTooShort);
+            mint_config.pending_fees = Some(params.fees);
+            mint_config.fees_effective_at = fees_effective_at;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_owners(ctx: Context<SetOwners>, owners: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        Multisig::validate(&owners, threshold)?;
+        let action = AdminAction::SetOwners {
+            owners: owners.clone(),
+            threshold,
+        };
+        ctx.accounts
+            .proposal
+            .execute(&ctx.accounts.multisig, &action)?;
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.owner_set_seqno = multisig
+            .owner_set_seqno
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Opens a proposal for an admin action; the proposer's approval is counted right away.
+    pub fn create_proposal(ctx: Context<CreateProposal>, action: AdminAction) -> Result<()> {
+        if let AdminAction::SetOwners { owners, threshold } = &action {
+            Multisig::validate(owners, *threshold)?;
+        }
+        let owner_index = ctx
+            .accounts
+            .multisig
+            .owner_index(ctx.accounts.proposer.key)?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.id = ctx.accounts.multisig.proposal_count;
+        proposal.proposer = *ctx.accounts.proposer.key;
+        proposal.action = action;
+        proposal.approvals = 1 << owner_index;
+        proposal.owner_set_seqno = ctx.accounts.multisig.owner_set_seqno;
+        proposal.executed = false;
+        proposal.bump = *ctx.bumps.get("proposal").unwrap();
+
+        ctx.accounts.multisig.proposal_count = ctx
+            .accounts
+            .multisig
+            .proposal_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
         Ok(())
     }
 
-    pub fn change_admin(ctx: Context<ChangeAdmin>) -> Result<()> {
-        ctx.accounts.admin_state.admin1 = *ctx.accounts.new_admin1.key;
-        ctx.accounts.admin_state.admin2 = *ctx.accounts.new_admin2.key;
-        ctx.accounts.admin_state.resolver = *ctx.accounts.new_resolver.key;
+    pub fn approve_proposal(ctx: Context<ApproveProposal>) -> Result<()> {
+        let owner_index = ctx.accounts.multisig.owner_index(ctx.accounts.owner.key)?;
+        ctx.accounts.proposal.approvals |= 1 << owner_index;
 
         Ok(())
     }
 
-    pub fn set_fee(ctx: Context<SetFee>, admin_fee: u64, resolver_fee: u64) -> Result<()> {
-        ctx.accounts.admin_state.admin_fee = admin_fee;
-        ctx.accounts.admin_state.resolver_fee = resolver_fee;
+    pub fn dispute(ctx: Context<Dispute>, milestone_idx: u64, reason_code: u16) -> Result<()> {
+        require!(
+            !ctx.accounts.admin_state.is_paused(PAUSE_DISPUTES),
+            ErrorCode::DisputesPaused
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let milestone = ctx.accounts.escrow_state.milestone_mut(milestone_idx)?;
+        require!(
+            milestone.status != MilestoneStatus::Disputed,
+            ErrorCode::AlreadyDisputed
+        );
+        let prior_status = milestone.status;
+        milestone.transition(MilestoneStatus::Disputed)?;
+        let seq = milestone.dispute_count;
+        milestone.dispute_count = seq.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        let disputed_amount = milestone.amount;
+        ctx.accounts.escrow_state.open_disputes = ctx
+            .accounts
+            .escrow_state
+            .open_disputes
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let dispute_record = &mut ctx.accounts.dispute_record;
+        dispute_record.escrow = ctx.accounts.escrow_state.key();
+        dispute_record.milestone_idx = milestone_idx;
+        dispute_record.seq = seq;
+        dispute_record.opener = *ctx.accounts.disputor.key;
+        dispute_record.reason_code = reason_code;
+        dispute_record.prior_status = prior_status;
+        dispute_record.opened_at = now;
+        dispute_record.bond_amount = ctx.accounts.mint_config.dispute_bond;
+        dispute_record.resolved_at = None;
+        dispute_record.taker_share_bps = None;
+        dispute_record.withdrawn_at = None;
+        dispute_record.evidence = Vec::new();
+        dispute_record.bump = *ctx.bumps.get("dispute_record").unwrap();
+        dispute_record.bond_vault_bump = *ctx.bumps.get("bond_vault").unwrap();
+
+        let bond_amount = dispute_record.bond_amount;
+        if bond_amount > 0 {
+            token::transfer(
+                ctx.accounts.into_transfer_to_bond_vault_context(),
+                bond_amount,
+            )?;
+        }
+
+        let mint_stats = &mut ctx.accounts.mint_stats;
+        mint_stats.disputed_amount = mint_stats
+            .disputed_amount
+            .checked_add(disputed_amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let admin_state = &mut ctx.accounts.admin_state;
+        admin_state.disputed_escrow = admin_state
+            .disputed_escrow
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+
+        emit!(DisputeOpened {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            milestone_idx,
+            dispute_record: ctx.accounts.dispute_record.key(),
+            opened_by: *ctx.accounts.disputor.key,
+            reason_code,
+            amount: disputed_amount,
+            bond_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Appends a hash of off-chain evidence to an unresolved dispute. Either party can submit.
+    pub fn submit_evidence(ctx: Context<SubmitEvidence>, evidence_hash: [u8; 32]) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let dispute_record = &mut ctx.accounts.dispute_record;
+        require!(dispute_record.is_open(), ErrorCode::DisputeClosed);
+        require!(
+            dispute_record.has_evidence_room(ctx.accounts.submitter.key),
+            ErrorCode::EvidenceFull
+        );
+        dispute_record.evidence.push(Evidence {
+            submitter: *ctx.accounts.submitter.key,
+            hash: evidence_hash,
+            submitted_at: now,
+        });
 
         Ok(())
     }
 
-    pub fn dispute(ctx: Context<Dispute>) -> Result<()> {
-        ctx.accounts.escrow_state.dispute_status = true;
+    /// Drops a dispute and refunds its bond. Only the opener can sign, so a settlement
+    /// between both parties goes through them as well.
+    pub fn withdraw_dispute(ctx: Context<WithdrawDispute>, milestone_idx: u64) -> Result<()> {
+        require!(
+            ctx.accounts.dispute_record.is_open(),
+            ErrorCode::DisputeClosed
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.dispute_record.withdrawn_at = Some(now);
+        let escrow_key = ctx.accounts.escrow_state.key();
+        let authority_bump = ctx.accounts.escrow_state.authority_bump;
+        let authority_seeds = &[AUTHORITY_SEED, escrow_key.as_ref(), &[authority_bump]];
+        let prior_status = ctx.accounts.dispute_record.prior_status;
+        let milestone = ctx.accounts.escrow_state.milestone_mut(milestone_idx)?;
+        // emergency_withdraw already cleared the dispute; only the bond is left
+        if milestone.status != MilestoneStatus::Recovered {
+            milestone.withdraw_dispute(prior_status)?;
+            let disputed_amount = milestone.amount;
+            ctx.accounts.escrow_state.open_disputes = ctx
+                .accounts
+                .escrow_state
+                .open_disputes
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
+
+            let mint_stats = &mut ctx.accounts.mint_stats;
+            mint_stats.disputed_amount = mint_stats
+                .disputed_amount
+                .checked_sub(disputed_amount)
+                .ok_or(ErrorCode::Overflow)?;
+
+            let admin_state = &mut ctx.accounts.admin_state;
+            admin_state.withdrawn_dispute = admin_state
+                .withdrawn_dispute
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        let bond_amount = ctx.accounts.dispute_record.bond_amount;
+        if bond_amount > 0 {
+            token::transfer(
+                ctx.accounts
+                    .into_refund_bond_context()
+                    .with_signer(&[&authority_seeds[..]]),
+                bond_amount,
+            )?;
+        }
+        token::close_account(
+            ctx.accounts
+                .into_close_bond_vault_context()
+                .with_signer(&[&authority_seeds[..]]),
+        )?;
+
+        emit!(DisputeWithdrawn {
+            escrow: ctx.accounts.escrow_state.key(),
+            initializer: ctx.accounts.escrow_state.initializer_key,
+            taker: ctx.accounts.escrow_state.taker,
+            milestone_idx,
+            dispute_record: ctx.accounts.dispute_record.key(),
+            withdrawn_by: *ctx.accounts.opener.key,
+            bond_amount,
+        });
 
         Ok(())
     }
@@ -321,6 +1470,14 @@ pub struct InitAdmin<'info> {
          space = AdminState::space()
      )]
     pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(
+        init,
+        seeds = [b"multisig".as_ref()],
+        bump,
+        payer = admin1,
+        space = Multisig::space()
+    )]
+    pub multisig: Box<Account<'info, Multisig>>,
     /// CHECK: This is not dangerous because we don't read or write from this account
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
@@ -329,10 +1486,7 @@ pub struct InitAdmin<'info> {
 }
 
 #[derive(Accounts)]
-pub struct ChangeAdmin<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub admin1: Signer<'info>,
+pub struct ProposeAdmin<'info> {
     /// CHECK: This is not dangerous because we don't read or write from this account
     #[account(mut)]
     pub new_admin1: AccountInfo<'info>,
@@ -344,7 +1498,25 @@ pub struct ChangeAdmin<'info> {
     pub new_resolver: AccountInfo<'info>,
     #[account(
         mut,
-        constraint = admin_state.admin1 == *admin1.key,
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptRole<'info> {
+    pub new_key: Signer<'info>,
+    #[account(
+        mut,
         seeds = [b"state".as_ref(), b"admin".as_ref()],
         bump = admin_state.bump
     )]
@@ -352,62 +1524,213 @@ pub struct ChangeAdmin<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(admin_fee: u64, resolver_fee: u64)]
+#[instruction(admin_fee_bps: u16, resolver_fee_bps: u16, cancel_fee_bps: u16, admin1_share_bps: u16, effective_at: i64)]
 pub struct SetFee<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub admin1: Signer<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
     #[account(
         mut,
-        constraint = admin_state.admin1 == *admin1.key,
         seeds = [b"state".as_ref(), b"admin".as_ref()],
         bump = admin_state.bump
     )]
     pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
 }
 
 #[derive(Accounts)]
-#[instruction(escrow_seed: u64, initializer_amount: [u64;5])]
-pub struct Initialize<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub initializer: Signer<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub taker: AccountInfo<'info>,
+pub struct SetConfig<'info> {
     #[account(
         mut,
         seeds = [b"state".as_ref(), b"admin".as_ref()],
         bump = admin_state.bump
     )]
     pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(Accounts)]
+pub struct AddMint<'info> {
     #[account(mut)]
+    pub payer: Signer<'info>,
     pub mint: Account<'info, Mint>,
     #[account(
         init,
-        seeds = [b"vault".as_ref(), &escrow_seed.to_le_bytes()],
+        seeds = [b"mint".as_ref(), mint.key().as_ref()],
         bump,
-        payer = initializer,
-        token::mint = mint,
-        token::authority = initializer,
+        payer = payer,
+        space = MintConfig::space()
     )]
-    pub vault: Account<'info, TokenAccount>,
-    #[account(
-        mut,
-        token::mint = mint,
-        token::authority = initializer,
-        constraint = initializer_deposit_token_account.amount >=(initializer_amount[0]+initializer_amount[1]+initializer_amount[2]+initializer_amount[3]+initializer_amount[4])
-    )]
-    pub initializer_deposit_token_account: Account<'info, TokenAccount>,
+    pub mint_config: Box<Account<'info, MintConfig>>,
     #[account(
         init,
-        seeds = [b"state".as_ref(), &escrow_seed.to_le_bytes()],
+        seeds = [b"stats".as_ref(), mint.key().as_ref()],
         bump,
-        payer = initializer,
-        space = EscrowState::space()
+        payer = payer,
+        space = MintStats::space()
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    #[account(seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMintConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"mint".as_ref(), mint_config.mint.as_ref()],
+        bump = mint_config.bump
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+    #[account(seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+}
+
+#[derive(Accounts)]
+pub struct SetOwners<'info> {
+    #[account(mut, seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProposal<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(mut, seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        init,
+        seeds = [b"proposal".as_ref(), &multisig.proposal_count.to_le_bytes()],
+        bump,
+        payer = proposer,
+        space = Proposal::space()
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveProposal<'info> {
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = !proposal.executed @ ErrorCode::ProposalAlreadyExecuted,
+        constraint = proposal.owner_set_seqno == multisig.owner_set_seqno @ ErrorCode::StaleProposal,
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestones: Vec<MilestoneParams>, expires_at: i64)]
+pub struct Initialize<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut)]
+    pub taker: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"mint".as_ref(), mint.key().as_ref()],
+        bump = mint_config.bump,
+        constraint = mint_config.enabled @ ErrorCode::MintNotEnabled,
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), mint.key().as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    #[account(
+        mut,
+        seeds = [b"counter".as_ref(), initializer.key().as_ref()],
+        bump = escrow_counter.bump
+    )]
+    pub escrow_counter: Box<Account<'info, EscrowCounter>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = initializer,
+        constraint = initializer_deposit_token_account.amount >= MilestoneParams::total_amount(&milestones)? @ ErrorCode::InsufficientFunds
+    )]
+    pub initializer_deposit_token_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        seeds = [b"state".as_ref(), initializer.key().as_ref(), &escrow_counter.next_id.to_le_bytes()],
+        bump,
+        payer = initializer,
+        // capped so an oversized list reaches the InvalidMilestoneCount check in the handler
+        space = EscrowState::space(milestones.len().min(MAX_MILESTONES))
     )]
     pub escrow_state: Box<Account<'info, EscrowState>>,
+    #[account(
+        init,
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
+        bump,
+        payer = initializer,
+        token::mint = mint,
+        token::authority = initializer,
+    )]
+    pub vault: Account<'info, TokenAccount>,
+    /// CHECK: PDA that owns this escrow's vault, only used as a CPI signer
+    #[account(seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()], bump)]
+    pub vault_authority: AccountInfo<'info>,
     /// CHECK: This is not dangerous because we don't read or write from this account
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
@@ -416,6 +1739,72 @@ pub struct Initialize<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(milestones: Vec<MilestoneParams>)]
+pub struct AddMilestones<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
+        bump = escrow_state.bump,
+        constraint = escrow_state.initializer_key == *initializer.key @ ErrorCode::Unauthorized,
+        // capped so an oversized list reaches the InvalidMilestoneCount check in the handler
+        realloc = EscrowState::space((escrow_state.milestones.len() + milestones.len()).min(MAX_MILESTONES)),
+        realloc::payer = initializer,
+        realloc::zero = false,
+    )]
+    pub escrow_state: Box<Account<'info, EscrowState>>,
+    #[account(
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(
+        seeds = [b"mint".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_config.bump,
+        constraint = mint_config.enabled @ ErrorCode::MintNotEnabled,
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = initializer,
+        constraint = initializer_deposit_token_account.amount >= MilestoneParams::total_amount(&milestones)? @ ErrorCode::InsufficientFunds
+    )]
+    pub initializer_deposit_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
+        bump = escrow_state.vault_bump
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitCounter<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"counter".as_ref(), initializer.key().as_ref()],
+        bump,
+        payer = initializer,
+        space = EscrowCounter::space()
+    )]
+    pub escrow_counter: Box<Account<'info, EscrowCounter>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_idx: u64)]
 pub struct Dispute<'info> {
     /// CHECK: This is not dangerous because we don't read or write from this account
     #[account(mut)]
@@ -423,112 +1812,216 @@ pub struct Dispute<'info> {
     /// CHECK: This is not dangerous because we don't read or write from this account
     #[account(
         mut,
-        seeds = [b"state".as_ref(), &escrow_state.random_seed.to_le_bytes()],
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
         bump = escrow_state.bump,
-        constraint = escrow_state.initializer_key == *disputor.key || escrow_state.taker == *disputor.key,
+        constraint = escrow_state.initializer_key == *disputor.key || escrow_state.taker == *disputor.key @ ErrorCode::Unauthorized,
     )]
     pub escrow_state: Box<Account<'info, EscrowState>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    #[account(
+        init,
+        seeds = [
+            b"dispute".as_ref(),
+            escrow_state.key().as_ref(),
+            &milestone_idx.to_le_bytes(),
+            &escrow_state.next_dispute_seq(milestone_idx).to_le_bytes(),
+        ],
+        bump,
+        payer = disputor,
+        space = DisputeRecord::space()
+    )]
+    pub dispute_record: Box<Account<'info, DisputeRecord>>,
+    #[account(
+        seeds = [b"mint".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_config.bump
+    )]
+    pub mint_config: Box<Account<'info, MintConfig>>,
+    #[account(address = escrow_state.mint)]
+    pub mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = disputor,
+        constraint = disputor_token_account.amount >= mint_config.dispute_bond @ ErrorCode::InsufficientFunds
+    )]
+    pub disputor_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        init,
+        seeds = [b"bond".as_ref(), dispute_record.key().as_ref()],
+        bump,
+        payer = disputor,
+        token::mint = mint,
+        token::authority = vault_authority,
+    )]
+    pub bond_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: PDA that owns this escrow's vaults, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: Program<'info, Token>,
 }
 
-// used for resolver to withdraw money in the vault
 #[derive(Accounts)]
-pub struct WithdrawForResolve<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub resolver: Signer<'info>,
+pub struct SubmitEvidence<'info> {
+    pub submitter: Signer<'info>,
+    #[account(
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
+        bump = escrow_state.bump,
+        constraint = escrow_state.initializer_key == *submitter.key || escrow_state.taker == *submitter.key @ ErrorCode::Unauthorized,
+    )]
+    pub escrow_state: Box<Account<'info, EscrowState>>,
     #[account(
         mut,
-        seeds = [b"vault".as_ref(), &escrow_state.random_seed.to_le_bytes()],
-        bump = escrow_state.vault_bump
+        seeds = [
+            b"dispute".as_ref(),
+            escrow_state.key().as_ref(),
+            &dispute_record.milestone_idx.to_le_bytes(),
+            &dispute_record.seq.to_le_bytes(),
+        ],
+        bump = dispute_record.bump
     )]
-    pub vault: Account<'info, TokenAccount>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub vault_authority: AccountInfo<'info>,
+    pub dispute_record: Box<Account<'info, DisputeRecord>>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_idx: u64)]
+pub struct WithdrawDispute<'info> {
+    #[account(mut, address = dispute_record.opener @ ErrorCode::Unauthorized)]
+    pub opener: Signer<'info>,
     #[account(
         mut,
         token::mint = escrow_state.mint,
-        token::authority = resolver,
+        token::authority = opener,
     )]
-    pub resolver_token_account: Account<'info, TokenAccount>,
+    pub opener_token_account: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
-        seeds = [b"state".as_ref(), &escrow_state.random_seed.to_le_bytes()],
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
         bump = escrow_state.bump,
-        close = resolver
     )]
     pub escrow_state: Box<Account<'info, EscrowState>>,
     #[account(
         mut,
         seeds = [b"state".as_ref(), b"admin".as_ref()],
-        bump = admin_state.bump,
-        constraint = admin_state.admin1 == *resolver.key,
+        bump = admin_state.bump
     )]
     pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    #[account(
+        mut,
+        seeds = [
+            b"dispute".as_ref(),
+            escrow_state.key().as_ref(),
+            &milestone_idx.to_le_bytes(),
+            &dispute_record.seq.to_le_bytes(),
+        ],
+        bump = dispute_record.bump
+    )]
+    pub dispute_record: Box<Account<'info, DisputeRecord>>,
+    #[account(
+        mut,
+        seeds = [b"bond".as_ref(), dispute_record.key().as_ref()],
+        bump = dispute_record.bond_vault_bump
+    )]
+    pub bond_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: PDA that owns this escrow's vaults, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
     /// CHECK: This is not dangerous because we don't read or write from this account
     pub token_program: Program<'info, Token>,
 }
 
+// the multisig recovers a stuck escrow; the vault rent goes back to the initializer
 #[derive(Accounts)]
-#[instruction(milestone_idx: u64)]
-pub struct Approve<'info> {
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub initializer: Signer<'info>,
-    // /// CHECK: This is not dangerous because we don't read or write from this account
-    // #[account(mut)]
-    // pub taker: AccountInfo<'info>,
+pub struct EmergencyWithdraw<'info> {
+    /// CHECK: Constrained to `escrow_state.initializer_key`; only receives the vault's rent lamports
     #[account(
         mut,
-        token::mint = escrow_state.mint,
-        token::authority = escrow_state.taker,
+        constraint = escrow_state.initializer_key == *initializer.key @ ErrorCode::Unauthorized,
     )]
-    pub taker_token_account: Box<Account<'info, TokenAccount>>,
+    pub initializer: AccountInfo<'info>,
     #[account(
         mut,
-        token::mint = escrow_state.mint,
-        token::authority = admin_state.admin1,
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
+        bump = escrow_state.vault_bump
     )]
-    pub admin1_token_account: Box<Account<'info, TokenAccount>>,
+    pub vault: Account<'info, TokenAccount>,
+    /// CHECK: PDA that owns this escrow's vault, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
     #[account(
         mut,
         token::mint = escrow_state.mint,
-        token::authority = admin_state.admin2,
+        constraint = recipient_token_account.owner == escrow_state.initializer_key
+            || recipient_token_account.owner == escrow_state.taker
+            || recipient_token_account.owner == admin_state.recovery_address @ ErrorCode::InvalidRecoveryRecipient,
     )]
-    pub admin2_token_account: Box<Account<'info, TokenAccount>>,
+    pub recipient_token_account: Account<'info, TokenAccount>,
     #[account(
         mut,
-        seeds = [b"state".as_ref(), &escrow_state.random_seed.to_le_bytes()],
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
         bump = escrow_state.bump,
-        constraint = escrow_state.taker == taker_token_account.owner,
-        constraint = escrow_state.initializer_key == *initializer.key,
-        constraint = escrow_state.initializer_amount[milestone_idx as usize] > 0,
-        constraint = escrow_state.dispute_status == false,
+        constraint = !escrow_state.is_settled() @ ErrorCode::NothingToRecover,
+        constraint = escrow_state.open_disputes > 0 || admin_state.is_paused(PAUSE_PAYOUTS) @ ErrorCode::RecoveryNotAllowed,
     )]
     pub escrow_state: Box<Account<'info, EscrowState>>,
     #[account(
         mut,
         seeds = [b"state".as_ref(), b"admin".as_ref()],
         bump = admin_state.bump,
-        constraint = admin_state.admin1 == admin1_token_account.owner,
-        constraint = admin_state.admin2 == admin2_token_account.owner,
     )]
     pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(seeds = [b"multisig".as_ref()], bump = multisig.bump)]
+    pub multisig: Box<Account<'info, Multisig>>,
     #[account(
         mut,
-        seeds = [b"vault".as_ref(), &escrow_state.random_seed.to_le_bytes()],
-        bump = escrow_state.vault_bump
+        seeds = [b"proposal".as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump
     )]
-    pub vault: Box<Account<'info, TokenAccount>>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub vault_authority: AccountInfo<'info>,
+    pub proposal: Box<Account<'info, Proposal>>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
     /// CHECK: This is not dangerous because we don't read or write from this account
     pub token_program: Program<'info, Token>,
 }
 
+// both parties must sign to cancel; rent of the state and vault goes back to the initializer
 #[derive(Accounts)]
-pub struct Refund<'info> {
+pub struct Cancel<'info> {
     /// CHECK: This is not dangerous because we don't read or write from this account
     #[account(mut)]
+    pub initializer: Signer<'info>,
     pub taker: Signer<'info>,
     #[account(
         mut,
@@ -550,44 +2043,55 @@ pub struct Refund<'info> {
     pub admin2_token_account: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
-        seeds = [b"state".as_ref(), &escrow_state.random_seed.to_le_bytes()],
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
         bump = escrow_state.bump,
-        constraint = escrow_state.initializer_key == initializer_deposit_token_account.owner,
-        constraint = escrow_state.taker == *taker.key,
-        constraint = escrow_state.dispute_status == false,
+        constraint = escrow_state.initializer_key == *initializer.key @ ErrorCode::Unauthorized,
+        constraint = escrow_state.taker == *taker.key @ ErrorCode::Unauthorized,
+        constraint = escrow_state.open_disputes == 0 @ ErrorCode::EscrowDisputed,
+        close = initializer
     )]
     pub escrow_state: Box<Account<'info, EscrowState>>,
     #[account(
         mut,
         seeds = [b"state".as_ref(), b"admin".as_ref()],
         bump = admin_state.bump,
-        constraint = admin_state.admin1 == admin1_token_account.owner,
-        constraint = admin_state.admin2 == admin2_token_account.owner,
+        constraint = admin_state.admin1 == admin1_token_account.owner @ ErrorCode::InvalidTokenAccount,
+        constraint = admin_state.admin2 == admin2_token_account.owner @ ErrorCode::InvalidTokenAccount,
     )]
     pub admin_state: Box<Account<'info, AdminState>>,
     #[account(
         mut,
-        seeds = [b"vault".as_ref(), &escrow_state.random_seed.to_le_bytes()],
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
         bump = escrow_state.vault_bump
     )]
     pub vault: Box<Account<'info, TokenAccount>>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
+    /// CHECK: PDA that owns this escrow's vault, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
     pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
     /// CHECK: This is not dangerous because we don't read or write from this account
     pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(milestone_idx: u64)]
-pub struct Resolve<'info> {
+pub struct ReclaimExpired<'info> {
     /// CHECK: This is not dangerous because we don't read or write from this account
     #[account(mut)]
-    pub resolver: Signer<'info>,
+    pub initializer: Signer<'info>,
     #[account(
         mut,
         token::mint = escrow_state.mint,
+        token::authority = escrow_state.initializer_key,
     )]
-    pub taker_token_account: Box<Account<'info, TokenAccount>>, // taker can be client or receiver in escrow - here
+    pub initializer_deposit_token_account: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
         token::mint = escrow_state.mint,
@@ -602,81 +2106,1134 @@ pub struct Resolve<'info> {
     pub admin2_token_account: Box<Account<'info, TokenAccount>>,
     #[account(
         mut,
-        token::mint = escrow_state.mint,
-        token::authority = resolver,
-    )]
-    pub resolver_token_account: Box<Account<'info, TokenAccount>>,
-    #[account(
-        mut,
-        seeds = [b"state".as_ref(), &escrow_state.random_seed.to_le_bytes()],
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
         bump = escrow_state.bump,
-        constraint = escrow_state.taker == taker_token_account.owner || escrow_state.initializer_key == taker_token_account.owner,
-        constraint = escrow_state.initializer_amount[milestone_idx as usize] > 0,
-        constraint = escrow_state.dispute_status == true,
+        constraint = escrow_state.initializer_key == *initializer.key @ ErrorCode::Unauthorized,
     )]
     pub escrow_state: Box<Account<'info, EscrowState>>,
     #[account(
         mut,
         seeds = [b"state".as_ref(), b"admin".as_ref()],
         bump = admin_state.bump,
-        constraint = admin_state.admin1 == admin1_token_account.owner,
-        constraint = admin_state.admin2 == admin2_token_account.owner,
-        constraint = admin_state.resolver == resolver_token_account.owner,
+        constraint = admin_state.admin1 == admin1_token_account.owner @ ErrorCode::InvalidTokenAccount,
+        constraint = admin_state.admin2 == admin2_token_account.owner @ ErrorCode::InvalidTokenAccount,
     )]
     pub admin_state: Box<Account<'info, AdminState>>,
     #[account(
         mut,
-        seeds = [b"vault".as_ref(), &escrow_state.random_seed.to_le_bytes()],
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
         bump = escrow_state.vault_bump
     )]
     pub vault: Box<Account<'info, TokenAccount>>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
+    /// CHECK: PDA that owns this escrow's vault, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
     pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
     /// CHECK: This is not dangerous because we don't read or write from this account
     pub token_program: Program<'info, Token>,
 }
 
-#[account]
-pub struct AdminState {
-    pub bump: u8,
-    pub admin_fee: u64,
-    pub resolver_fee: u64,
-    pub admin1: Pubkey,
-    pub admin2: Pubkey,
+#[derive(Accounts)]
+pub struct SubmitMilestone<'info> {
+    pub taker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
+        bump = escrow_state.bump,
+        constraint = escrow_state.taker == *taker.key @ ErrorCode::Unauthorized,
+    )]
+    pub escrow_state: Box<Account<'info, EscrowState>>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_idx: u64)]
+pub struct Approve<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    // /// CHECK: This is not dangerous because we don't read or write from this account
+    // #[account(mut)]
+    // pub taker: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = escrow_state.taker,
+    )]
+    pub taker_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = admin_state.admin1,
+    )]
+    pub admin1_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = admin_state.admin2,
+    )]
+    pub admin2_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
+        bump = escrow_state.bump,
+        constraint = escrow_state.taker == taker_token_account.owner @ ErrorCode::InvalidTokenAccount,
+        constraint = escrow_state.initializer_key == *initializer.key @ ErrorCode::Unauthorized,
+    )]
+    pub escrow_state: Box<Account<'info, EscrowState>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump,
+        constraint = admin_state.admin1 == admin1_token_account.owner @ ErrorCode::InvalidTokenAccount,
+        constraint = admin_state.admin2 == admin2_token_account.owner @ ErrorCode::InvalidTokenAccount,
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(
+        mut,
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
+        bump = escrow_state.vault_bump
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: PDA that owns this escrow's vault, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: Program<'info, Token>,
+}
+
+// permissionless: anyone may crank a submitted milestone once its review window is over
+#[derive(Accounts)]
+#[instruction(milestone_idx: u64)]
+pub struct ClaimAfterTimeout<'info> {
+    pub caller: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = escrow_state.taker,
+    )]
+    pub taker_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = admin_state.admin1,
+    )]
+    pub admin1_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = admin_state.admin2,
+    )]
+    pub admin2_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
+        bump = escrow_state.bump,
+    )]
+    pub escrow_state: Box<Account<'info, EscrowState>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump,
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(
+        mut,
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
+        bump = escrow_state.vault_bump
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: PDA that owns this escrow's vault, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = escrow_state.initializer_key,
+    )]
+    pub initializer_deposit_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = admin_state.admin1,
+    )]
+    pub admin1_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = admin_state.admin2,
+    )]
+    pub admin2_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
+        bump = escrow_state.bump,
+        constraint = escrow_state.initializer_key == initializer_deposit_token_account.owner @ ErrorCode::InvalidTokenAccount,
+        constraint = escrow_state.taker == *taker.key @ ErrorCode::Unauthorized,
+    )]
+    pub escrow_state: Box<Account<'info, EscrowState>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump,
+        constraint = admin_state.admin1 == admin1_token_account.owner @ ErrorCode::InvalidTokenAccount,
+        constraint = admin_state.admin2 == admin2_token_account.owner @ ErrorCode::InvalidTokenAccount,
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(
+        mut,
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
+        bump = escrow_state.vault_bump
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: PDA that owns this escrow's vault, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestone_idx: u64)]
+pub struct Resolve<'info> {
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = escrow_state.taker,
+    )]
+    pub taker_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = escrow_state.initializer_key,
+    )]
+    pub initializer_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = admin_state.admin1,
+    )]
+    pub admin1_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = admin_state.admin2,
+    )]
+    pub admin2_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = escrow_state.mint,
+        token::authority = resolver,
+    )]
+    pub resolver_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), escrow_state.initializer_key.as_ref(), &escrow_state.escrow_id.to_le_bytes()],
+        bump = escrow_state.bump,
+        constraint = escrow_state.taker == taker_token_account.owner @ ErrorCode::InvalidTokenAccount,
+        constraint = escrow_state.initializer_key == initializer_token_account.owner @ ErrorCode::InvalidTokenAccount,
+    )]
+    pub escrow_state: Box<Account<'info, EscrowState>>,
+    #[account(
+        mut,
+        seeds = [b"state".as_ref(), b"admin".as_ref()],
+        bump = admin_state.bump,
+        constraint = admin_state.admin1 == admin1_token_account.owner @ ErrorCode::InvalidTokenAccount,
+        constraint = admin_state.admin2 == admin2_token_account.owner @ ErrorCode::InvalidTokenAccount,
+    )]
+    pub admin_state: Box<Account<'info, AdminState>>,
+    #[account(
+        mut,
+        seeds = [b"vault".as_ref(), escrow_state.key().as_ref()],
+        bump = escrow_state.vault_bump
+    )]
+    pub vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: PDA that owns this escrow's vault, only used as a CPI signer
+    #[account(
+        seeds = [AUTHORITY_SEED, escrow_state.key().as_ref()],
+        bump = escrow_state.authority_bump,
+    )]
+    pub vault_authority: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"stats".as_ref(), escrow_state.mint.as_ref()],
+        bump = mint_stats.bump
+    )]
+    pub mint_stats: Box<Account<'info, MintStats>>,
+    #[account(
+        mut,
+        seeds = [
+            b"dispute".as_ref(),
+            escrow_state.key().as_ref(),
+            &milestone_idx.to_le_bytes(),
+            &dispute_record.seq.to_le_bytes(),
+        ],
+        bump = dispute_record.bump
+    )]
+    pub dispute_record: Box<Account<'info, DisputeRecord>>,
+    #[account(
+        mut,
+        seeds = [b"bond".as_ref(), dispute_record.key().as_ref()],
+        bump = dispute_record.bond_vault_bump
+    )]
+    pub bond_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: Receives the bond vault's rent; checked against the dispute record
+    #[account(mut, address = dispute_record.opener @ ErrorCode::Unauthorized)]
+    pub opener: AccountInfo<'info>,
+    /// CHECK: This is not dangerous because we don't read or write from this account
+    pub token_program: Program<'info, Token>,
+}
+
+#[account]
+pub struct AdminState {
+    pub bump: u8,
+    pub fees: FeeSchedule,
+    pub admin1: Pubkey,
+    pub admin2: Pubkey,
     pub resolver: Pubkey,
-    pub total_amount: u64,
-    pub locked_amount: u64,
     pub active_escrow: u64,
     pub completed_escrow: u64,
+    /// Number of disputes opened.
     pub disputed_escrow: u64,
+    /// Disputed milestones the resolver paid out to the taker.
+    pub resolved_for_taker: u64,
+    /// Disputed milestones the resolver returned to the initializer.
+    pub resolved_for_initializer: u64,
+    /// Disputed milestones the resolver split between both parties.
+    pub resolved_split: u64,
     pub refunded_escrow: u64,
+    pub review_period: i64,
+    pub waive_expiry_fee: bool,
+    pub cancelled_escrow: u64,
+    /// `PAUSE_*` bits. Pausing payouts also allows `emergency_withdraw` on any open escrow.
+    pub pause_flags: u8,
+    /// Extra destination allowed for `emergency_withdraw` besides the escrow's own parties.
+    pub recovery_address: Pubkey,
+    pub recovered_escrow: u64,
+    pub pending_admin1: Option<Pubkey>,
+    pub pending_admin2: Option<Pubkey>,
+    pub pending_resolver: Option<Pubkey>,
+    /// Fee schedule announced by `set_fee`, charged to escrows created from `fees_effective_at`.
+    pub pending_fees: Option<FeeSchedule>,
+    pub fees_effective_at: i64,
+    /// Can rule on disputes the resolver has left open for `resolver_timeout` seconds.
+    pub backup_resolver: Pubkey,
+    /// Zero disables the backup resolver.
+    pub resolver_timeout: i64,
+    /// Disputes dropped by their opener through `withdraw_dispute`.
+    pub withdrawn_dispute: u64,
+}
+
+impl AdminState {
+    pub fn space() -> usize {
+        8 + 383
+    }
+
+    /// Whether `key` may rule on a dispute opened at `opened_at`.
+    pub fn can_resolve(&self, key: &Pubkey, opened_at: i64, now: i64) -> bool {
+        if *key == self.resolver {
+            return true;
+        }
+        *key == self.backup_resolver
+            && self.resolver_timeout > 0
+            && now >= opened_at.saturating_add(self.resolver_timeout)
+    }
+
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.pause_flags & flag != 0
+    }
+
+    /// Promotes the announced fee schedule once its effective time has been reached.
+    pub fn apply_pending_fees(&mut self, now: i64) {
+        if now >= self.fees_effective_at {
+            if let Some(fees) = self.pending_fees.take() {
+                self.fees = fees;
+            }
+        }
+    }
+
+    /// Hands every role staged for `key` over to it. Returns whether any role was pending.
+    pub fn accept_pending(&mut self, key: Pubkey) -> bool {
+        let mut accepted = false;
+        if self.pending_admin1 == Some(key) {
+            self.admin1 = key;
+            self.pending_admin1 = None;
+            accepted = true;
+        }
+        if self.pending_admin2 == Some(key) {
+            self.admin2 = key;
+            self.pending_admin2 = None;
+            accepted = true;
+        }
+        if self.pending_resolver == Some(key) {
+            self.resolver = key;
+            self.pending_resolver = None;
+            accepted = true;
+        }
+        accepted
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct FeeSchedule {
+    pub admin_fee_bps: u16,
+    pub resolver_fee_bps: u16,
+    pub cancel_fee_bps: u16,
+    /// Portion of every admin fee paid to admin1; admin2 receives the rest.
+    pub admin1_share_bps: u16,
+}
+
+#[allow(clippy::result_large_err)]
+impl FeeSchedule {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.admin_fee_bps as u64 + self.resolver_fee_bps as u64 <= MAX_FEE_BPS,
+            ErrorCode::FeeTooHigh
+        );
+        require!(
+            self.cancel_fee_bps as u64 <= MAX_FEE_BPS,
+            ErrorCode::FeeTooHigh
+        );
+        require!(
+            self.admin1_share_bps as u64 <= BPS_DENOMINATOR,
+            ErrorCode::InvalidFeeSplit
+        );
+        Ok(())
+    }
+
+    /// Splits `amount` into fee legs and the recipient's share. Every leg is rounded down and
+    /// the recipient absorbs the remainder, so the legs always add back up to `amount`.
+    pub fn split(
+        &self,
+        amount: u64,
+        admin_fee_bps: u16,
+        resolver_fee_bps: u16,
+    ) -> Result<FeeSplit> {
+        let admin_fee = mul_div(amount, admin_fee_bps as u64, BPS_DENOMINATOR)?;
+        let admin1_fee = mul_div(admin_fee, self.admin1_share_bps as u64, BPS_DENOMINATOR)?;
+        let admin2_fee = admin_fee
+            .checked_sub(admin1_fee)
+            .ok_or(ErrorCode::Overflow)?;
+        let resolver_fee = mul_div(amount, resolver_fee_bps as u64, BPS_DENOMINATOR)?;
+        let recipient_amount = amount
+            .checked_sub(admin_fee)
+            .and_then(|rest| rest.checked_sub(resolver_fee))
+            .ok_or(ErrorCode::FeeTooHigh)?;
+
+        Ok(FeeSplit {
+            recipient_amount,
+            admin1_fee,
+            admin2_fee,
+            resolver_fee,
+        })
+    }
+}
+
+#[account]
+pub struct Multisig {
+    pub bump: u8,
+    pub owners: Vec<Pubkey>,
+    pub threshold: u8,
+    /// Bumped whenever the owner set changes so older proposals can no longer execute.
+    pub owner_set_seqno: u32,
+    pub proposal_count: u64,
+}
+
+#[allow(clippy::result_large_err)]
+impl Multisig {
+    pub fn space() -> usize {
+        8 + 1 + 4 + 32 * MAX_OWNERS + 1 + 4 + 8
+    }
+
+    pub fn validate(owners: &[Pubkey], threshold: u8) -> Result<()> {
+        require!(
+            !owners.is_empty() && owners.len() <= MAX_OWNERS,
+            ErrorCode::InvalidOwners
+        );
+        require!(
+            owners
+                .iter()
+                .enumerate()
+                .all(|(i, owner)| !owners[..i].contains(owner)),
+            ErrorCode::InvalidOwners
+        );
+        require!(
+            threshold > 0 && threshold as usize <= owners.len(),
+            ErrorCode::InvalidThreshold
+        );
+        Ok(())
+    }
+
+    pub fn owner_index(&self, key: &Pubkey) -> Result<usize> {
+        self.owners
+            .iter()
+            .position(|owner| owner == key)
+            .ok_or_else(|| error!(ErrorCode::NotMultisigOwner))
+    }
+}
+
+#[account]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: AdminAction,
+    /// Bitmap of approving owners, indexed by their position in `Multisig::owners`.
+    pub approvals: u16,
+    pub owner_set_seqno: u32,
+    pub executed: bool,
+    pub bump: u8,
+}
+
+#[allow(clippy::result_large_err)]
+impl Proposal {
+    pub fn space() -> usize {
+        8 + 8 + 32 + AdminAction::SPACE + 2 + 4 + 1 + 1
+    }
+
+    /// Marks the proposal executed if it carries `action` and has reached the threshold.
+    pub fn execute(&mut self, multisig: &Multisig, action: &AdminAction) -> Result<()> {
+        require!(!self.executed, ErrorCode::ProposalAlreadyExecuted);
+        require!(
+            self.owner_set_seqno == multisig.owner_set_seqno,
+            ErrorCode::StaleProposal
+        );
+        require!(self.action == *action, ErrorCode::ProposalActionMismatch);
+        require!(
+            self.approvals.count_ones() >= multisig.threshold as u32,
+            ErrorCode::NotEnoughApprovals
+        );
+        self.executed = true;
+        Ok(())
+    }
+}
+
+/// Admin operations that have to go through a multisig proposal.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub enum AdminAction {
+    SetFee {
+        fees: FeeSchedule,
+        effective_at: i64,
+    },
+    ProposeAdmin {
+        admin1: Pubkey,
+        admin2: Pubkey,
+        resolver: Pubkey,
+    },
+    CancelAdminProposal,
+    SetReviewPeriod {
+        review_period: i64,
+    },
+    SetExpiryFeeWaiver {
+        waive_expiry_fee: bool,
+    },
+    SetPauseFlags {
+        pause_flags: u8,
+    },
+    SetRecoveryAddress {
+        recovery_address: Pubkey,
+    },
+    SetBackupResolver {
+        backup_resolver: Pubkey,
+        resolver_timeout: i64,
+    },
+    EmergencyWithdraw {
+        escrow_state: Pubkey,
+        recipient_token_account: Pubkey,
+        reason_code: u16,
+    },
+    SetOwners {
+        owners: Vec<Pubkey>,
+        threshold: u8,
+    },
+    AddMint {
+        mint: Pubkey,
+        params: MintConfigParams,
+    },
+    UpdateMintConfig {
+        mint: Pubkey,
+        params: MintConfigParams,
+        fees_effective_at: i64,
+    },
+}
+
+impl AdminAction {
+    /// Size of the largest variant, `SetOwners` with a full owner set.
+    pub const SPACE: usize = 1 + 4 + 32 * MAX_OWNERS + 1;
+}
+
+/// Allowlist entry for a mint, with the fees and escrow size limits that apply to it.
+#[account]
+pub struct MintConfig {
+    pub mint: Pubkey,
+    pub enabled: bool,
+    /// `None` charges the global admin and resolver fees from `AdminState::fees`.
+    pub fees: Option<MintFees>,
+    pub min_amount: u64,
+    pub max_amount: u64,
+    /// Posted by whoever opens a dispute on an escrow in this mint; zero disables it.
+    pub dispute_bond: u64,
+    /// `Some(None)` drops the override once `fees_effective_at` is reached.
+    pub pending_fees: Option<Option<MintFees>>,
+    pub fees_effective_at: i64,
+    pub bump: u8,
+}
+
+impl MintConfig {
+    pub fn space() -> usize {
+        8 + 77
+    }
+
+    pub fn apply_pending_fees(&mut self, now: i64) {
+        if now >= self.fees_effective_at {
+            if let Some(fees) = self.pending_fees.take() {
+                self.fees = fees;
+            }
+        }
+    }
+
+    /// Fee schedule a new escrow in this mint is charged, with the mint's override applied.
+    pub fn escrow_fees(&self, global: &FeeSchedule) -> FeeSchedule {
+        match self.fees {
+            Some(fees) => FeeSchedule {
+                admin_fee_bps: fees.admin_fee_bps,
+                resolver_fee_bps: fees.resolver_fee_bps,
+                ..*global
+            },
+            None => *global,
+        }
+    }
+}
+
+/// Running totals for a single mint, all in that mint's base units.
+#[account]
+pub struct MintStats {
+    pub mint: Pubkey,
+    pub total_deposited: u64,
+    pub locked_amount: u64,
+    pub paid_to_takers: u64,
+    pub refunded: u64,
+    pub fees_collected: u64,
+    /// Funds moved out by `emergency_withdraw`.
+    pub recovered: u64,
+    /// Value of the milestones currently under dispute.
+    pub disputed_amount: u64,
+    /// Dispute bonds paid to the resolver after a ruling against the opener.
+    pub forfeited_bonds: u64,
+    pub bump: u8,
+}
+
+impl MintStats {
+    pub fn space() -> usize {
+        8 + 97
+    }
+}
+
+/// Per-mint override of the admin and resolver fees; the cancel fee and admin split stay global.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct MintFees {
+    pub admin_fee_bps: u16,
+    pub resolver_fee_bps: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct MintConfigParams {
+    pub enabled: bool,
+    pub fees: Option<MintFees>,
+    pub min_amount: u64,
+    pub max_amount: u64,
+    pub dispute_bond: u64,
+}
+
+#[allow(clippy::result_large_err)]
+impl MintConfigParams {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(fees) = self.fees {
+            require!(
+                fees.admin_fee_bps as u64 + fees.resolver_fee_bps as u64 <= MAX_FEE_BPS,
+                ErrorCode::FeeTooHigh
+            );
+        }
+        require!(
+            self.min_amount <= self.max_amount,
+            ErrorCode::InvalidMintLimits
+        );
+        Ok(())
+    }
+}
+
+pub struct FeeSplit {
+    pub recipient_amount: u64,
+    pub admin1_fee: u64,
+    pub admin2_fee: u64,
+    pub resolver_fee: u64,
+}
+
+#[allow(clippy::result_large_err)]
+impl FeeSplit {
+    pub fn total_fees(&self) -> Result<u64> {
+        self.admin1_fee
+            .checked_add(self.admin2_fee)
+            .and_then(|fees| fees.checked_add(self.resolver_fee))
+            .ok_or_else(|| error!(ErrorCode::Overflow))
+    }
+}
+
+/// Hands out sequential escrow ids per initializer.
+#[account]
+pub struct EscrowCounter {
+    pub initializer: Pubkey,
+    pub next_id: u64,
+    pub bump: u8,
+}
+
+impl EscrowCounter {
+    pub fn space() -> usize {
+        8 + 41
+    }
+}
+
+#[account]
+pub struct EscrowState {
+    pub escrow_id: u64,
+    pub initializer_key: Pubkey,
+    pub taker: Pubkey,
+    pub milestones: Vec<Milestone>,
+    /// Number of milestones currently under dispute.
+    pub open_disputes: u8,
+    pub refund_status: bool,
+    pub mint: Pubkey,
+    pub bump: u8,
+    pub vault_bump: u8,
+    pub authority_bump: u8,
+    pub review_period: i64,
+    pub expires_at: i64,
+    /// Reason code recorded by `emergency_withdraw`.
+    pub recovery_reason: Option<u16>,
+    /// Fee schedule in force when the escrow was created; every payout is charged by it.
+    pub fees: FeeSchedule,
+}
+
+#[allow(clippy::result_large_err)]
+impl EscrowState {
+    pub fn space(milestone_count: usize) -> usize {
+        8 + 140 + Milestone::SPACE * milestone_count
+    }
+
+    /// Sum of the milestones whose funds are still held in the vault.
+    pub fn remaining_amount(&self) -> Result<u64> {
+        self.milestones
+            .iter()
+            .filter(|milestone| milestone.status.is_open())
+            .try_fold(0u64, |total, milestone| total.checked_add(milestone.amount))
+            .ok_or_else(|| error!(ErrorCode::Overflow))
+    }
+
+    /// Whether the taker has submitted work or either party has disputed a milestone.
+    pub fn is_started(&self) -> bool {
+        self.milestones.iter().any(|milestone| {
+            milestone.status != MilestoneStatus::Pending || milestone.dispute_count > 0
+        })
+    }
+
+    /// Whether every milestone has reached a final status.
+    pub fn is_settled(&self) -> bool {
+        !self
+            .milestones
+            .iter()
+            .any(|milestone| milestone.status.is_open())
+    }
+
+    pub fn milestone_mut(&mut self, milestone_idx: u64) -> Result<&mut Milestone> {
+        self.milestones
+            .get_mut(milestone_idx as usize)
+            .ok_or_else(|| error!(ErrorCode::InvalidMilestoneIndex))
+    }
+
+    /// Sequence number of the next dispute on a milestone. An out-of-range index yields zero
+    /// and is rejected by the handler.
+    pub fn next_dispute_seq(&self, milestone_idx: u64) -> u16 {
+        self.milestones
+            .get(milestone_idx as usize)
+            .map_or(0, |milestone| milestone.dispute_count)
+    }
+}
+
+/// Audit trail for a dispute on a single milestone.
+#[account]
+pub struct DisputeRecord {
+    pub escrow: Pubkey,
+    pub milestone_idx: u64,
+    /// Position among the disputes opened on this milestone.
+    pub seq: u16,
+    pub opener: Pubkey,
+    pub reason_code: u16,
+    /// Status the milestone goes back to if the dispute is withdrawn.
+    pub prior_status: MilestoneStatus,
+    pub opened_at: i64,
+    /// Held in the bond vault until the dispute is resolved.
+    pub bond_amount: u64,
+    pub resolved_at: Option<i64>,
+    pub taker_share_bps: Option<u16>,
+    /// Set by `withdraw_dispute`; the record is kept for the audit trail.
+    pub withdrawn_at: Option<i64>,
+    /// Append-only; capped at `MAX_EVIDENCE` entries per party.
+    pub evidence: Vec<Evidence>,
+    pub bump: u8,
+    pub bond_vault_bump: u8,
+}
+
+impl DisputeRecord {
+    pub fn space() -> usize {
+        // room for both parties to fill their quota
+        8 + 116 + 4 + Evidence::SPACE * MAX_EVIDENCE * 2
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.resolved_at.is_none() && self.withdrawn_at.is_none()
+    }
+
+    /// Whether `submitter` is still under its own evidence quota.
+    pub fn has_evidence_room(&self, submitter: &Pubkey) -> bool {
+        self.evidence
+            .iter()
+            .filter(|evidence| evidence.submitter == *submitter)
+            .count()
+            < MAX_EVIDENCE
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Evidence {
+    pub submitter: Pubkey,
+    /// Digest of the evidence file or URI kept off-chain.
+    pub hash: [u8; 32],
+    pub submitted_at: i64,
+}
+
+impl Evidence {
+    pub const SPACE: usize = 32 + 32 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MilestoneParams {
+    pub amount: u64,
+    pub spec_hash: [u8; 32],
+    pub due_ts: Option<i64>,
+}
+
+#[allow(clippy::result_large_err)]
+impl MilestoneParams {
+    pub fn total_amount(milestones: &[MilestoneParams]) -> Result<u64> {
+        milestones
+            .iter()
+            .try_fold(0u64, |total, milestone| total.checked_add(milestone.amount))
+            .ok_or_else(|| error!(ErrorCode::Overflow))
+    }
+
+    fn into_milestone(self) -> Milestone {
+        Milestone {
+            amount: self.amount,
+            spec_hash: self.spec_hash,
+            due_ts: self.due_ts,
+            status: MilestoneStatus::Pending,
+            deliverable_hash: [0; 32],
+            submitted_at: None,
+            taker_share_bps: None,
+            dispute_count: 0,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Milestone {
+    pub amount: u64,
+    pub spec_hash: [u8; 32],
+    pub due_ts: Option<i64>,
+    pub status: MilestoneStatus,
+    /// Digest of the deliverable (file hash or URI) recorded by the taker.
+    pub deliverable_hash: [u8; 32],
+    pub submitted_at: Option<i64>,
+    /// Taker's share of the milestone, after fees, awarded by the resolver.
+    pub taker_share_bps: Option<u16>,
+    /// Disputes opened on this milestone so far; seeds the next dispute record.
+    pub dispute_count: u16,
+}
+
+#[allow(clippy::result_large_err)]
+impl Milestone {
+    pub const SPACE: usize = 8 + 32 + 9 + 1 + 32 + 9 + 3 + 2;
+
+    /// When the taker can claim a submitted milestone that the initializer has not reviewed.
+    pub fn review_ends_at(&self, review_period: i64) -> Result<i64> {
+        require!(
+            self.status == MilestoneStatus::Submitted,
+            ErrorCode::MilestoneNotSubmitted
+        );
+        self.submitted_at
+            .ok_or(ErrorCode::MilestoneNotSubmitted)?
+            .checked_add(review_period)
+            .ok_or_else(|| error!(ErrorCode::Overflow))
+    }
+
+    /// Puts a disputed milestone back to where it was when the dispute opened. `submitted_at`
+    /// is left alone so a dispute cannot restart the review window.
+    pub fn withdraw_dispute(&mut self, prior_status: MilestoneStatus) -> Result<()> {
+        require!(
+            self.status == MilestoneStatus::Disputed,
+            ErrorCode::NotDisputed
+        );
+        self.transition(prior_status)
+    }
+
+    pub fn transition(&mut self, next: MilestoneStatus) -> Result<()> {
+        require!(
+            self.status.can_transition_to(next),
+            ErrorCode::InvalidMilestoneTransition
+        );
+        self.status = next;
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MilestoneStatus {
+    Pending,
+    Submitted,
+    Approved,
+    Disputed,
+    Resolved,
+    Refunded,
+    Recovered,
+}
+
+impl MilestoneStatus {
+    /// Whether the milestone still has funds locked in the vault.
+    pub fn is_open(self) -> bool {
+        matches!(
+            self,
+            MilestoneStatus::Pending | MilestoneStatus::Submitted | MilestoneStatus::Disputed
+        )
+    }
+
+    pub fn can_transition_to(self, next: MilestoneStatus) -> bool {
+        use MilestoneStatus::*;
+
+        matches!(
+            (self, next),
+            (Pending, Submitted)
+                | (Pending, Approved)
+                | (Submitted, Approved)
+                | (Pending, Disputed)
+                | (Submitted, Disputed)
+                | (Disputed, Resolved)
+                | (Disputed, Pending)
+                | (Disputed, Submitted)
+                | (Pending, Refunded)
+                | (Submitted, Refunded)
+                | (Pending, Recovered)
+                | (Submitted, Recovered)
+                | (Disputed, Recovered)
+        )
+    }
+}
+
+#[event]
+pub struct EscrowCreated {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub mint: Pubkey,
+    pub escrow_id: u64,
+    pub amount: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct MilestonesAdded {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    /// Milestones on the escrow after this call.
+    pub milestone_count: u64,
+    pub amount: u64,
+    /// Everything now locked in the vault for this escrow.
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct MilestoneSubmitted {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub milestone_idx: u64,
+    pub amount: u64,
+    pub deliverable_hash: [u8; 32],
+}
+
+/// Emitted for approvals by the initializer and for claims after the review period.
+#[event]
+pub struct MilestoneApproved {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub milestone_idx: u64,
+    pub amount: u64,
+    pub taker_amount: u64,
+    pub admin1_fee: u64,
+    pub admin2_fee: u64,
+}
+
+#[event]
+pub struct Refunded {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub amount: u64,
+    pub initializer_amount: u64,
+    pub admin1_fee: u64,
+    pub admin2_fee: u64,
+}
+
+/// `initializer_amount` also carries anything deposited into the vault outside of `initialize`.
+#[event]
+pub struct EscrowCancelled {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub amount: u64,
+    pub initializer_amount: u64,
+    pub admin1_fee: u64,
+    pub admin2_fee: u64,
 }
 
-impl AdminState {
-    pub fn space() -> usize {
-        8 + 161
-    }
+#[event]
+pub struct ExpiredReclaimed {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub amount: u64,
+    pub initializer_amount: u64,
+    pub admin1_fee: u64,
+    pub admin2_fee: u64,
 }
 
-#[account]
-pub struct EscrowState {
-    pub random_seed: u64,
-    pub initializer_key: Pubkey,
+/// `amount` is what the open milestones held; `recovered_amount` is the whole vault balance.
+#[event]
+pub struct EmergencyWithdrawn {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
     pub taker: Pubkey,
-    pub initializer_amount: [u64; 5],
-    pub dispute_status: bool,
-    pub refund_status: bool,
-    pub mint: Pubkey,
-    pub bump: u8,
-    pub vault_bump: u8,
+    pub recipient_token_account: Pubkey,
+    pub amount: u64,
+    pub recovered_amount: u64,
+    pub reason_code: u16,
 }
 
-impl EscrowState {
-    pub fn space() -> usize {
-        8 + 148
-    }
+#[event]
+pub struct DisputeOpened {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub milestone_idx: u64,
+    pub dispute_record: Pubkey,
+    pub opened_by: Pubkey,
+    pub reason_code: u16,
+    pub amount: u64,
+    pub bond_amount: u64,
 }
 
+#[event]
+pub struct DisputeResolved {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub milestone_idx: u64,
+    pub dispute_record: Pubkey,
+    pub taker_share_bps: u16,
+    pub amount: u64,
+    pub taker_amount: u64,
+    pub initializer_amount: u64,
+    pub admin1_fee: u64,
+    pub admin2_fee: u64,
+    pub resolver_fee: u64,
+    pub bond_amount: u64,
+    pub bond_forfeited: bool,
+    pub resolved_by: Pubkey,
+}
+
+#[event]
+pub struct DisputeWithdrawn {
+    pub escrow: Pubkey,
+    pub initializer: Pubkey,
+    pub taker: Pubkey,
+    pub milestone_idx: u64,
+    pub dispute_record: Pubkey,
+    pub withdrawn_by: Pubkey,
+    pub bond_amount: u64,
+}
+
+/// Emitted when a fee schedule is announced; it applies to escrows created from `effective_at`.
+#[event]
+pub struct FeesChanged {
+    pub fees: FeeSchedule,
+    pub effective_at: i64,
+}
+
+/// Emitted whenever a pending role is accepted, with the resulting role keys.
+#[event]
+pub struct AdminChanged {
+    pub admin1: Pubkey,
+    pub admin2: Pubkey,
+    pub resolver: Pubkey,
+}
+
+#[allow(clippy::wrong_self_convention)]
 impl<'info> Initialize<'info> {
     fn into_transfer_to_pda_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
@@ -696,11 +3253,24 @@ impl<'info> Initialize<'info> {
     }
 }
 
-impl<'info> WithdrawForResolve<'info> {
-    fn into_transfer_to_resolver_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+#[allow(clippy::wrong_self_convention)]
+impl<'info> AddMilestones<'info> {
+    fn into_transfer_to_vault_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.initializer_deposit_token_account.to_account_info(),
+            to: self.vault.to_account_info(),
+            authority: self.initializer.to_account_info(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+#[allow(clippy::wrong_self_convention)]
+impl<'info> EmergencyWithdraw<'info> {
+    fn into_transfer_to_recipient_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
             from: self.vault.to_account_info(),
-            to: self.resolver_token_account.to_account_info(),
+            to: self.recipient_token_account.to_account_info(),
             authority: self.vault_authority.clone(),
         };
         CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
@@ -709,13 +3279,14 @@ impl<'info> WithdrawForResolve<'info> {
     fn into_close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
         let cpi_accounts = CloseAccount {
             account: self.vault.to_account_info(),
-            destination: self.resolver.to_account_info(),
+            destination: self.initializer.to_account_info(),
             authority: self.vault_authority.clone(),
         };
         CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
     }
 }
 
+#[allow(clippy::wrong_self_convention)]
 impl<'info> Approve<'info> {
     fn into_transfer_to_taker_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
@@ -745,6 +3316,119 @@ impl<'info> Approve<'info> {
     }
 }
 
+#[allow(clippy::wrong_self_convention)]
+impl<'info> Cancel<'info> {
+    fn into_transfer_to_initializer_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.initializer_deposit_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_transfer_to_admin1_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.admin1_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_transfer_to_admin2_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.admin2_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.vault.to_account_info(),
+            destination: self.initializer.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+#[allow(clippy::wrong_self_convention)]
+impl<'info> ReclaimExpired<'info> {
+    fn into_transfer_to_initializer_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.initializer_deposit_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_transfer_to_admin1_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.admin1_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_transfer_to_admin2_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.admin2_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_close_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.vault.to_account_info(),
+            destination: self.initializer.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+#[allow(clippy::wrong_self_convention)]
+impl<'info> ClaimAfterTimeout<'info> {
+    fn into_transfer_to_taker_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.taker_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_transfer_to_admin1_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.admin1_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_transfer_to_admin2_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.admin2_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+#[allow(clippy::wrong_self_convention)]
 impl<'info> Refund<'info> {
     fn into_transfer_to_initializer_context(
         &self,
@@ -776,6 +3460,42 @@ impl<'info> Refund<'info> {
     }
 }
 
+#[allow(clippy::wrong_self_convention)]
+impl<'info> Dispute<'info> {
+    fn into_transfer_to_bond_vault_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.disputor_token_account.to_account_info(),
+            to: self.bond_vault.to_account_info(),
+            authority: self.disputor.to_account_info(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+#[allow(clippy::wrong_self_convention)]
+impl<'info> WithdrawDispute<'info> {
+    fn into_refund_bond_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.bond_vault.to_account_info(),
+            to: self.opener_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_close_bond_vault_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.bond_vault.to_account_info(),
+            destination: self.opener.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+#[allow(clippy::wrong_self_convention)]
 impl<'info> Resolve<'info> {
     fn into_transfer_to_taker_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
@@ -786,6 +3506,17 @@ impl<'info> Resolve<'info> {
         CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
     }
 
+    fn into_transfer_to_initializer_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.vault.to_account_info(),
+            to: self.initializer_token_account.to_account_info(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
     fn into_transfer_to_admin1_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
         let cpi_accounts = Transfer {
             from: self.vault.to_account_info(),
@@ -812,4 +3543,517 @@ impl<'info> Resolve<'info> {
         };
         CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
     }
+
+    fn into_transfer_bond_context(
+        &self,
+        to: AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.bond_vault.to_account_info(),
+            to,
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+
+    fn into_close_bond_vault_context(&self) -> CpiContext<'_, '_, '_, 'info, CloseAccount<'info>> {
+        let cpi_accounts = CloseAccount {
+            account: self.bond_vault.to_account_info(),
+            destination: self.opener.clone(),
+            authority: self.vault_authority.clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+/// Computes `amount * numerator / denominator` without intermediate overflow.
+#[allow(clippy::result_large_err)]
+pub fn mul_div(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    let value = (amount as u128)
+        .checked_mul(numerator as u128)
+        .and_then(|value| value.checked_div(denominator as u128))
+        .ok_or(ErrorCode::Overflow)?;
+    u64::try_from(value).map_err(|_| error!(ErrorCode::Overflow))
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Milestone index is out of range")]
+    InvalidMilestoneIndex,
+    #[msg("Milestone cannot move to the requested status")]
+    InvalidMilestoneTransition,
+    #[msg("Milestone has not been submitted")]
+    MilestoneNotSubmitted,
+    #[msg("Review period has not elapsed yet")]
+    ReviewPeriodNotElapsed,
+    #[msg("Review period must be positive")]
+    InvalidReviewPeriod,
+    #[msg("Expiry must be in the future")]
+    InvalidExpiry,
+    #[msg("Escrow has expired")]
+    EscrowExpired,
+    #[msg("Escrow has not expired yet")]
+    EscrowNotExpired,
+    #[msg("No unsubmitted milestones left to reclaim")]
+    NothingToReclaim,
+    #[msg("Milestone count must be between 1 and MAX_MILESTONES")]
+    InvalidMilestoneCount,
+    #[msg("Deposit token account does not hold enough funds")]
+    InsufficientFunds,
+    #[msg("Signer is not allowed to perform this action")]
+    Unauthorized,
+    #[msg("Token account does not belong to the expected owner")]
+    InvalidTokenAccount,
+    #[msg("Escrow has milestones under dispute")]
+    EscrowDisputed,
+    #[msg("Milestone is not under dispute")]
+    NotDisputed,
+    #[msg("Milestone is already disputed")]
+    AlreadyDisputed,
+    #[msg("No undisputed milestones left to refund")]
+    NothingToRefund,
+    #[msg("Fees exceed the allowed maximum")]
+    FeeTooHigh,
+    #[msg("Admin fee split must not exceed 10000 basis points")]
+    InvalidFeeSplit,
+    #[msg("New escrows are paused")]
+    NewEscrowsPaused,
+    #[msg("Payouts are paused")]
+    PayoutsPaused,
+    #[msg("Disputes are paused")]
+    DisputesPaused,
+    #[msg("Unknown pause flags")]
+    InvalidPauseFlags,
+    #[msg("Emergency withdraw needs an active dispute or paused payouts")]
+    RecoveryNotAllowed,
+    #[msg("Recovered funds can only go to the escrow parties or the recovery address")]
+    InvalidRecoveryRecipient,
+    #[msg("Escrow has no open milestones to recover")]
+    NothingToRecover,
+    #[msg("Signer has no pending admin role to accept")]
+    NoPendingRole,
+    #[msg("Owners must be unique and between 1 and MAX_OWNERS")]
+    InvalidOwners,
+    #[msg("Threshold must be between 1 and the number of owners")]
+    InvalidThreshold,
+    #[msg("Signer is not a multisig owner")]
+    NotMultisigOwner,
+    #[msg("Proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Proposal was created for a previous owner set")]
+    StaleProposal,
+    #[msg("Proposal does not match the requested action")]
+    ProposalActionMismatch,
+    #[msg("Proposal has not reached the approval threshold")]
+    NotEnoughApprovals,
+    #[msg("Fee changes must be announced at least MIN_FEE_DELAY in advance")]
+    FeeDelayTooShort,
+    #[msg("Mint is not enabled for escrows")]
+    MintNotEnabled,
+    #[msg("Escrow amount is outside the mint's limits")]
+    EscrowAmountOutOfRange,
+    #[msg("Minimum escrow amount exceeds the maximum")]
+    InvalidMintLimits,
+    #[msg("Taker share must not exceed 10000 basis points")]
+    InvalidTakerShare,
+    #[msg("Dispute has already been resolved or withdrawn")]
+    DisputeClosed,
+    #[msg("Dispute record cannot hold more evidence")]
+    EvidenceFull,
+    #[msg("Resolver timeout must not be negative")]
+    InvalidResolverTimeout,
+    #[msg("Milestones can only be added before work on the escrow starts")]
+    EscrowStarted,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee_schedule(admin1_share_bps: u16) -> FeeSchedule {
+        FeeSchedule {
+            admin_fee_bps: 0,
+            resolver_fee_bps: 0,
+            cancel_fee_bps: 0,
+            admin1_share_bps,
+        }
+    }
+
+    fn admin_state(resolver: Pubkey, backup_resolver: Pubkey, resolver_timeout: i64) -> AdminState {
+        AdminState {
+            bump: 0,
+            fees: fee_schedule(DEFAULT_ADMIN1_SHARE_BPS),
+            admin1: Pubkey::new_unique(),
+            admin2: Pubkey::new_unique(),
+            resolver,
+            active_escrow: 0,
+            completed_escrow: 0,
+            disputed_escrow: 0,
+            resolved_for_taker: 0,
+            resolved_for_initializer: 0,
+            resolved_split: 0,
+            refunded_escrow: 0,
+            review_period: DEFAULT_REVIEW_PERIOD,
+            waive_expiry_fee: false,
+            cancelled_escrow: 0,
+            pause_flags: 0,
+            recovery_address: Pubkey::default(),
+            recovered_escrow: 0,
+            pending_admin1: None,
+            pending_admin2: None,
+            pending_resolver: None,
+            pending_fees: None,
+            fees_effective_at: 0,
+            backup_resolver,
+            resolver_timeout,
+            withdrawn_dispute: 0,
+        }
+    }
+
+    fn dispute_record(opener: Pubkey) -> DisputeRecord {
+        DisputeRecord {
+            escrow: Pubkey::new_unique(),
+            milestone_idx: 0,
+            seq: 0,
+            opener,
+            reason_code: 0,
+            prior_status: MilestoneStatus::Submitted,
+            opened_at: 0,
+            bond_amount: 0,
+            resolved_at: None,
+            taker_share_bps: None,
+            withdrawn_at: None,
+            evidence: Vec::new(),
+            bump: 0,
+            bond_vault_bump: 0,
+        }
+    }
+
+    fn multisig(owners: usize, threshold: u8) -> Multisig {
+        Multisig {
+            bump: 0,
+            owners: (0..owners).map(|_| Pubkey::new_unique()).collect(),
+            threshold,
+            owner_set_seqno: 0,
+            proposal_count: 0,
+        }
+    }
+
+    fn proposal(action: AdminAction, approvals: u16) -> Proposal {
+        Proposal {
+            id: 0,
+            proposer: Pubkey::new_unique(),
+            action,
+            approvals,
+            owner_set_seqno: 0,
+            executed: false,
+            bump: 0,
+        }
+    }
+
+    fn milestone(status: MilestoneStatus) -> Milestone {
+        Milestone {
+            amount: 100,
+            spec_hash: [0; 32],
+            due_ts: None,
+            status,
+            deliverable_hash: [0; 32],
+            submitted_at: None,
+            taker_share_bps: None,
+            dispute_count: 0,
+        }
+    }
+
+    #[test]
+    fn split_rounds_fees_down_and_gives_the_remainder_to_the_recipient() {
+        let split = fee_schedule(1_500).split(10_001, 250, 100).unwrap();
+        assert_eq!(split.admin1_fee, 37);
+        assert_eq!(split.admin2_fee, 213);
+        assert_eq!(split.resolver_fee, 100);
+        assert_eq!(split.recipient_amount, 9_651);
+    }
+
+    #[test]
+    fn split_legs_always_add_up_to_the_amount() {
+        for &share in &[0, 1, 1_500, 3_333, BPS_DENOMINATOR as u16] {
+            let fees = fee_schedule(share);
+            for &amount in &[0, 1, 7, 99, 10_001, 123_456_789, u64::MAX] {
+                let split = fees.split(amount, 1_999, 501).unwrap();
+                let total = split.total_fees().unwrap() + split.recipient_amount;
+                assert_eq!(total, amount);
+            }
+        }
+    }
+
+    #[test]
+    fn split_without_fees_pays_everything_to_the_recipient() {
+        let split = fee_schedule(1_500).split(1_000, 0, 0).unwrap();
+        assert_eq!(split.recipient_amount, 1_000);
+        assert_eq!(split.total_fees().unwrap(), 0);
+    }
+
+    #[test]
+    fn mint_fees_override_the_global_schedule_only_when_set() {
+        let global = FeeSchedule {
+            admin_fee_bps: 300,
+            resolver_fee_bps: 100,
+            cancel_fee_bps: 50,
+            admin1_share_bps: DEFAULT_ADMIN1_SHARE_BPS,
+        };
+        let mut mint_config = MintConfig {
+            mint: Pubkey::new_unique(),
+            enabled: true,
+            fees: None,
+            min_amount: 0,
+            max_amount: u64::MAX,
+            dispute_bond: 0,
+            pending_fees: None,
+            fees_effective_at: 0,
+            bump: 0,
+        };
+        assert!(mint_config.escrow_fees(&global) == global);
+
+        mint_config.fees = Some(MintFees {
+            admin_fee_bps: 20,
+            resolver_fee_bps: 10,
+        });
+        let fees = mint_config.escrow_fees(&global);
+        assert_eq!(fees.admin_fee_bps, 20);
+        assert_eq!(fees.resolver_fee_bps, 10);
+        assert_eq!(fees.cancel_fee_bps, 50);
+        assert_eq!(fees.admin1_share_bps, DEFAULT_ADMIN1_SHARE_BPS);
+    }
+
+    #[test]
+    fn mul_div_does_not_overflow_in_the_intermediate_product() {
+        assert_eq!(
+            mul_div(u64::MAX, BPS_DENOMINATOR, BPS_DENOMINATOR).unwrap(),
+            u64::MAX
+        );
+        assert_eq!(mul_div(9_999, 5_000, BPS_DENOMINATOR).unwrap(), 4_999);
+        assert!(mul_div(u64::MAX, 2, 1).is_err());
+    }
+
+    #[test]
+    fn settled_milestones_reject_every_transition() {
+        use MilestoneStatus::*;
+
+        let all = [
+            Pending, Submitted, Approved, Disputed, Resolved, Refunded, Recovered,
+        ];
+        for &settled in &[Approved, Resolved, Refunded, Recovered] {
+            assert!(!settled.is_open());
+            for &next in &all {
+                let mut milestone = milestone(settled);
+                assert_eq!(
+                    milestone.transition(next).unwrap_err(),
+                    ErrorCode::InvalidMilestoneTransition.into(),
+                    "{:?} -> {:?}",
+                    settled,
+                    next
+                );
+                assert_eq!(milestone.status, settled);
+            }
+        }
+    }
+
+    #[test]
+    fn disputed_milestones_cannot_be_approved() {
+        let mut milestone = milestone(MilestoneStatus::Disputed);
+        assert_eq!(
+            milestone.transition(MilestoneStatus::Approved).unwrap_err(),
+            ErrorCode::InvalidMilestoneTransition.into()
+        );
+        assert_eq!(milestone.status, MilestoneStatus::Disputed);
+
+        milestone.transition(MilestoneStatus::Resolved).unwrap();
+        assert_eq!(milestone.status, MilestoneStatus::Resolved);
+    }
+
+    #[test]
+    fn submitted_milestones_cannot_go_back_to_pending() {
+        let mut milestone = milestone(MilestoneStatus::Pending);
+        milestone.transition(MilestoneStatus::Submitted).unwrap();
+        assert_eq!(
+            milestone.transition(MilestoneStatus::Pending).unwrap_err(),
+            ErrorCode::InvalidMilestoneTransition.into()
+        );
+        milestone.transition(MilestoneStatus::Approved).unwrap();
+        assert_eq!(milestone.status, MilestoneStatus::Approved);
+    }
+
+    #[test]
+    fn withdrawn_dispute_keeps_the_original_review_window() {
+        let mut milestone = milestone(MilestoneStatus::Submitted);
+        milestone.submitted_at = Some(1_000);
+        let review_ends_at = milestone.review_ends_at(DEFAULT_REVIEW_PERIOD).unwrap();
+        assert_eq!(review_ends_at, 1_000 + DEFAULT_REVIEW_PERIOD);
+
+        // disputed just before the window closes, then withdrawn
+        milestone.transition(MilestoneStatus::Disputed).unwrap();
+        assert_eq!(
+            milestone.review_ends_at(DEFAULT_REVIEW_PERIOD).unwrap_err(),
+            ErrorCode::MilestoneNotSubmitted.into()
+        );
+        milestone
+            .withdraw_dispute(MilestoneStatus::Submitted)
+            .unwrap();
+
+        assert_eq!(milestone.status, MilestoneStatus::Submitted);
+        assert_eq!(
+            milestone.review_ends_at(DEFAULT_REVIEW_PERIOD).unwrap(),
+            review_ends_at
+        );
+    }
+
+    #[test]
+    fn only_disputed_milestones_can_withdraw_a_dispute() {
+        let mut pending = milestone(MilestoneStatus::Pending);
+        assert_eq!(
+            pending
+                .withdraw_dispute(MilestoneStatus::Pending)
+                .unwrap_err(),
+            ErrorCode::NotDisputed.into()
+        );
+
+        let mut disputed = milestone(MilestoneStatus::Disputed);
+        disputed.withdraw_dispute(MilestoneStatus::Pending).unwrap();
+        assert_eq!(disputed.status, MilestoneStatus::Pending);
+        assert_eq!(
+            disputed
+                .withdraw_dispute(MilestoneStatus::Pending)
+                .unwrap_err(),
+            ErrorCode::NotDisputed.into()
+        );
+    }
+
+    #[test]
+    fn each_party_gets_its_own_evidence_quota() {
+        let taker = Pubkey::new_unique();
+        let initializer = Pubkey::new_unique();
+        let mut record = dispute_record(taker);
+        for _ in 0..MAX_EVIDENCE {
+            assert!(record.has_evidence_room(&taker));
+            record.evidence.push(Evidence {
+                submitter: taker,
+                hash: [0; 32],
+                submitted_at: 0,
+            });
+        }
+        assert!(!record.has_evidence_room(&taker));
+        assert!(record.has_evidence_room(&initializer));
+
+        for _ in 0..MAX_EVIDENCE {
+            record.evidence.push(Evidence {
+                submitter: initializer,
+                hash: [0; 32],
+                submitted_at: 0,
+            });
+        }
+        assert!(!record.has_evidence_room(&initializer));
+        assert!(record.try_to_vec().unwrap().len() + 8 <= DisputeRecord::space());
+    }
+
+    #[test]
+    fn multisig_rejects_bad_owner_sets() {
+        let owners: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        assert!(Multisig::validate(&owners, 2).is_ok());
+        assert_eq!(
+            Multisig::validate(&[], 1).unwrap_err(),
+            ErrorCode::InvalidOwners.into()
+        );
+        let too_many: Vec<Pubkey> = (0..=MAX_OWNERS).map(|_| Pubkey::new_unique()).collect();
+        assert_eq!(
+            Multisig::validate(&too_many, 1).unwrap_err(),
+            ErrorCode::InvalidOwners.into()
+        );
+        let duplicated = [owners[0], owners[1], owners[0]];
+        assert_eq!(
+            Multisig::validate(&duplicated, 1).unwrap_err(),
+            ErrorCode::InvalidOwners.into()
+        );
+        assert_eq!(
+            Multisig::validate(&owners, 0).unwrap_err(),
+            ErrorCode::InvalidThreshold.into()
+        );
+        assert_eq!(
+            Multisig::validate(&owners, 4).unwrap_err(),
+            ErrorCode::InvalidThreshold.into()
+        );
+    }
+
+    #[test]
+    fn proposal_executes_once_with_enough_approvals() {
+        let multisig = multisig(3, 2);
+        let action = AdminAction::SetReviewPeriod { review_period: 60 };
+
+        let mut pending = proposal(action.clone(), 0b001);
+        assert_eq!(
+            pending.execute(&multisig, &action).unwrap_err(),
+            ErrorCode::NotEnoughApprovals.into()
+        );
+        assert!(!pending.executed);
+
+        let mut approved = proposal(action.clone(), 0b101);
+        approved.execute(&multisig, &action).unwrap();
+        assert!(approved.executed);
+        assert_eq!(
+            approved.execute(&multisig, &action).unwrap_err(),
+            ErrorCode::ProposalAlreadyExecuted.into()
+        );
+    }
+
+    #[test]
+    fn proposal_only_executes_the_action_it_carries() {
+        let multisig = multisig(3, 2);
+        let mut approved = proposal(AdminAction::SetReviewPeriod { review_period: 60 }, 0b011);
+        assert_eq!(
+            approved
+                .execute(
+                    &multisig,
+                    &AdminAction::SetReviewPeriod { review_period: 61 }
+                )
+                .unwrap_err(),
+            ErrorCode::ProposalActionMismatch.into()
+        );
+        assert!(!approved.executed);
+    }
+
+    #[test]
+    fn proposal_goes_stale_when_the_owner_set_changes() {
+        let mut multisig = multisig(3, 2);
+        let action = AdminAction::CancelAdminProposal;
+        let mut approved = proposal(action.clone(), 0b111);
+        multisig.owner_set_seqno += 1;
+        assert_eq!(
+            approved.execute(&multisig, &action).unwrap_err(),
+            ErrorCode::StaleProposal.into()
+        );
+    }
+
+    #[test]
+    fn backup_resolver_waits_for_the_timeout() {
+        let resolver = Pubkey::new_unique();
+        let backup = Pubkey::new_unique();
+        let stranger = Pubkey::new_unique();
+        let admin_state = admin_state(resolver, backup, 100);
+
+        assert!(admin_state.can_resolve(&resolver, 1_000, 1_000));
+        assert!(!admin_state.can_resolve(&backup, 1_000, 1_099));
+        assert!(admin_state.can_resolve(&backup, 1_000, 1_100));
+        assert!(!admin_state.can_resolve(&stranger, 1_000, 10_000));
+        assert!(!admin_state.can_resolve(&backup, i64::MAX - 10, i64::MAX - 1));
+    }
+
+    #[test]
+    fn backup_resolver_is_disabled_without_a_timeout() {
+        let resolver = Pubkey::new_unique();
+        let backup = Pubkey::new_unique();
+        let admin_state = admin_state(resolver, backup, 0);
+
+        assert!(admin_state.can_resolve(&resolver, 0, 0));
+        assert!(!admin_state.can_resolve(&backup, 0, i64::MAX));
+    }
 }
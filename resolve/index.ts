@@ -11,6 +11,14 @@ function wait(milliseconds) {
   });
 }
 
+function requireEnv(name: string): string {
+  const value = process.env[name];
+  if (!value) {
+    throw new Error(`${name} must be set, e.g. ${name}=<base58 address> yarn resolve`);
+  }
+  return value;
+}
+
 const resolver = anchor.web3.Keypair.fromSecretKey(
   new Uint8Array([
     167, 235, 82, 154, 192, 129, 184, 82, 193, 236, 49, 76, 173, 215, 178, 140, 85, 143, 232, 173, 126, 217, 195, 142,
@@ -52,32 +60,63 @@ describe("anchor-escrow", () => {
   const stateSeed = "state";
   const vaultSeed = "vault";
   const authoritySeed = "authority";
+  const statsSeed = "stats";
+  const disputeSeed = "dispute";
+  const bondSeed = "bond";
 
   const adminKey = PublicKey.findProgramAddressSync(
     [Buffer.from(anchor.utils.bytes.utf8.encode(stateSeed)), Buffer.from(anchor.utils.bytes.utf8.encode(adminSeed))],
     program.programId
   )[0];
 
-  const vaultAuthorityKey = PublicKey.findProgramAddressSync(
-    [Buffer.from(anchor.utils.bytes.utf8.encode(authoritySeed))],
+  const mintStatsKey = PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode(statsSeed)), mintA.toBuffer()],
     program.programId
   )[0];
 
   it("Solve the real dispute", async () => {
-    const tmpSeed: anchor.BN = new anchor.BN(54913206);
+    // Escrows are addressed by their initializer and the id handed out by its counter
+    const initializerKey = new PublicKey(requireEnv("ESCROW_INITIALIZER"));
+    const initializerTokenAccount = new PublicKey(requireEnv("INITIALIZER_TOKEN_ACCOUNT"));
+    const escrowId: anchor.BN = new anchor.BN(process.env.ESCROW_ID ?? 0);
     const escrowStateKey = PublicKey.findProgramAddressSync(
-      [Buffer.from(anchor.utils.bytes.utf8.encode(stateSeed)), tmpSeed.toArrayLike(Buffer, "le", 8)],
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode(stateSeed)),
+        initializerKey.toBuffer(),
+        escrowId.toArrayLike(Buffer, "le", 8),
+      ],
       program.programId
     )[0];
     const vaultKey = PublicKey.findProgramAddressSync(
-      [Buffer.from(anchor.utils.bytes.utf8.encode(vaultSeed)), tmpSeed.toArrayLike(Buffer, "le", 8)],
+      [Buffer.from(anchor.utils.bytes.utf8.encode(vaultSeed)), escrowStateKey.toBuffer()],
+      program.programId
+    )[0];
+    const disputeRecordKey = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from(anchor.utils.bytes.utf8.encode(disputeSeed)),
+        escrowStateKey.toBuffer(),
+        new anchor.BN(0).toArrayLike(Buffer, "le", 8),
+        // one record per dispute opened on the milestone, starting at 0
+        new anchor.BN(process.env.DISPUTE_SEQ ?? 0).toArrayLike(Buffer, "le", 2),
+      ],
+      program.programId
+    )[0];
+    const bondVaultKey = PublicKey.findProgramAddressSync(
+      [Buffer.from(anchor.utils.bytes.utf8.encode(bondSeed)), disputeRecordKey.toBuffer()],
+      program.programId
+    )[0];
+    // The bond vault's rent goes back to whoever opened the dispute
+    const disputeRecord = await program.account.disputeRecord.fetch(disputeRecordKey);
+    const vaultAuthorityKey = PublicKey.findProgramAddressSync(
+      [Buffer.from(anchor.utils.bytes.utf8.encode(authoritySeed)), escrowStateKey.toBuffer()],
       program.programId
     )[0];
     await program.methods
-      .resolve(new anchor.BN(0))
+      .resolve(new anchor.BN(0), Number(process.env.TAKER_SHARE_BPS ?? 10000))
       .accounts({
         resolver: wallet.publicKey,
         takerTokenAccount: new PublicKey("FUXaFmc5xqKcSX1sdXVuEP5iYWjBSpYeWHxSRCwGxRmU"),
+        initializerTokenAccount,
         admin1TokenAccount: new PublicKey("6ch2CPNLzjaCgjS7dQBgBNSrjBWVhYBDHzFERmghPqX"),
         admin2TokenAccount: new PublicKey("6a1SizqF4Mrgb1sqxHRXCe4g6UiUbGSa1qQQbZR8Tge3"),
         resolverTokenAccount: new PublicKey("CSRpjKrcXFBvWGPC1SVCbBozywWWqkAx8fTh3vvAfMn9"),
@@ -85,6 +124,10 @@ describe("anchor-escrow", () => {
         adminState: adminKey,
         vault: vaultKey,
         vaultAuthority: vaultAuthorityKey,
+        mintStats: mintStatsKey,
+        disputeRecord: disputeRecordKey,
+        bondVault: bondVaultKey,
+        opener: disputeRecord.opener,
         tokenProgram: TOKEN_PROGRAM_ID,
       })
       .signers([resolver])
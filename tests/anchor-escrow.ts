@@ -58,45 +58,92 @@ describe("anchor-escrow", () => {
 
   // Determined Seeds
   const adminSeed = "admin";
+  const multisigSeed = "multisig";
+  const proposalSeed = "proposal";
   const stateSeed = "state";
   const vaultSeed = "vault";
   const authoritySeed = "authority";
+  const counterSeed = "counter";
+  const mintSeed = "mint";
+  const statsSeed = "stats";
+  const disputeSeed = "dispute";
+  const bondSeed = "bond";
 
-  // Random Seed
-  const randomSeed: anchor.BN = new anchor.BN(Math.floor(Math.random() * 100000000));
+  // Escrow ids handed out by the initializer's counter
+  const escrowId: anchor.BN = new anchor.BN(0);
 
   const adminKey = PublicKey.findProgramAddressSync(
     [Buffer.from(anchor.utils.bytes.utf8.encode(stateSeed)), Buffer.from(anchor.utils.bytes.utf8.encode(adminSeed))],
     program.programId
   )[0];
 
+  const multisigKey = PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode(multisigSeed))],
+    program.programId
+  )[0];
+
+  const escrowCounterKey = PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode(counterSeed)), initializer.publicKey.toBuffer()],
+    program.programId
+  )[0];
+
   // Derive PDAs: escrowStateKey, vaultKey, vaultAuthorityKey
   const escrowStateKey = PublicKey.findProgramAddressSync(
-    [Buffer.from(anchor.utils.bytes.utf8.encode(stateSeed)), randomSeed.toArrayLike(Buffer, "le", 8)],
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode(stateSeed)),
+      initializer.publicKey.toBuffer(),
+      escrowId.toArrayLike(Buffer, "le", 8),
+    ],
     program.programId
   )[0];
 
   const vaultKey = PublicKey.findProgramAddressSync(
-    [Buffer.from(anchor.utils.bytes.utf8.encode(vaultSeed)), randomSeed.toArrayLike(Buffer, "le", 8)],
+    [Buffer.from(anchor.utils.bytes.utf8.encode(vaultSeed)), escrowStateKey.toBuffer()],
     program.programId
   )[0];
 
-  // Random Seed
-  const randomSeed2: anchor.BN = new anchor.BN(Math.floor(Math.random() * 100000000));
+  const escrowId2: anchor.BN = new anchor.BN(1);
 
   // Derive PDAs: escrowStateKey, vaultKey, vaultAuthorityKey
   const escrowStateKey2 = PublicKey.findProgramAddressSync(
-    [Buffer.from(anchor.utils.bytes.utf8.encode(stateSeed)), randomSeed2.toArrayLike(Buffer, "le", 8)],
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode(stateSeed)),
+      initializer.publicKey.toBuffer(),
+      escrowId2.toArrayLike(Buffer, "le", 8),
+    ],
     program.programId
   )[0];
 
   const vaultKey2 = PublicKey.findProgramAddressSync(
-    [Buffer.from(anchor.utils.bytes.utf8.encode(vaultSeed)), randomSeed2.toArrayLike(Buffer, "le", 8)],
+    [Buffer.from(anchor.utils.bytes.utf8.encode(vaultSeed)), escrowStateKey2.toBuffer()],
+    program.programId
+  )[0];
+
+  // Derived once mintA exists: [mintSeed, mintA] and [statsSeed, mintA]
+  let mintConfigKey = null as PublicKey;
+  let mintStatsKey = null as PublicKey;
+
+  const disputeRecordKey = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from(anchor.utils.bytes.utf8.encode(disputeSeed)),
+      escrowStateKey.toBuffer(),
+      new anchor.BN(0).toArrayLike(Buffer, "le", 8),
+      new anchor.BN(0).toArrayLike(Buffer, "le", 2),
+    ],
+    program.programId
+  )[0];
+  const bondVaultKey = PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode(bondSeed)), disputeRecordKey.toBuffer()],
     program.programId
   )[0];
 
   const vaultAuthorityKey = PublicKey.findProgramAddressSync(
-    [Buffer.from(anchor.utils.bytes.utf8.encode(authoritySeed))],
+    [Buffer.from(anchor.utils.bytes.utf8.encode(authoritySeed)), escrowStateKey.toBuffer()],
+    program.programId
+  )[0];
+
+  const vaultAuthorityKey2 = PublicKey.findProgramAddressSync(
+    [Buffer.from(anchor.utils.bytes.utf8.encode(authoritySeed)), escrowStateKey2.toBuffer()],
     program.programId
   )[0];
 
@@ -146,6 +193,14 @@ describe("anchor-escrow", () => {
   //   console.log(4);
   //   // 3. Create dummy token mints: mintA and mintB
   //   mintA = new PublicKey("Ad4JSN6xUeok3JVgow9LTJ8GW1K1y8W397nsZrNYYW5E");
+  //   mintConfigKey = PublicKey.findProgramAddressSync(
+  //     [Buffer.from(anchor.utils.bytes.utf8.encode(mintSeed)), mintA.toBuffer()],
+  //     program.programId
+  //   )[0];
+  //   mintStatsKey = PublicKey.findProgramAddressSync(
+  //     [Buffer.from(anchor.utils.bytes.utf8.encode(statsSeed)), mintA.toBuffer()],
+  //     program.programId
+  //   )[0];
 
   //   // 4. Create token accounts for dummy token mints and both main roles
   //   initializerTokenAccountA = await createAccount(connection, wallet.payer, mintA, initializer.publicKey);
@@ -192,12 +247,13 @@ describe("anchor-escrow", () => {
 
   it("init admin address", async () => {
     await program.methods
-      .initAdmin()
+      .initAdmin([wallet.publicKey], 1)
       .accounts({
         admin1: wallet.publicKey.toString(),
         admin2: admin2.toString(),
         resolver: resolver.publicKey.toString(),
         adminState: adminKey.toString(),
+        multisig: multisigKey.toString(),
         systemProgram: anchor.web3.SystemProgram.programId,
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         tokenProgram: TOKEN_PROGRAM_ID,
@@ -213,59 +269,112 @@ describe("anchor-escrow", () => {
     assert.ok(fetchedAdminState.resolver.toString() === resolver.publicKey.toString());
   });
 
-  // it("change admin address", async () => {
+  // it("propose admin address", async () => {
+  //   // The wallet is the only multisig owner, so its proposal is approved on creation.
+  //   const fetchedMultisig: any = await program.account.multisig.fetch(multisigKey);
+  //   const proposalKey = PublicKey.findProgramAddressSync(
+  //     [
+  //       Buffer.from(anchor.utils.bytes.utf8.encode(proposalSeed)),
+  //       fetchedMultisig.proposalCount.toArrayLike(Buffer, "le", 8),
+  //     ],
+  //     program.programId
+  //   )[0];
   //   await program.methods
-  //     .changeAdmin()
+  //     .createProposal({
+  //       proposeAdmin: {
+  //         admin1: admin1.publicKey,
+  //         admin2,
+  //         resolver: resolver.publicKey,
+  //       },
+  //     })
   //     .accounts({
-  //       admin1: wallet.publicKey.toString(),
-  //       newAdmin1: wallet.publicKey.toString(),
+  //       proposer: wallet.publicKey,
+  //       multisig: multisigKey,
+  //       proposal: proposalKey,
+  //       systemProgram: anchor.web3.SystemProgram.programId,
+  //     })
+  //     .rpc();
+
+  //   await program.methods
+  //     .proposeAdmin()
+  //     .accounts({
+  //       newAdmin1: admin1.publicKey.toString(),
   //       newAdmin2: admin2.toString(),
   //       newResolver: resolver.publicKey.toString(),
   //       adminState: adminKey.toString(),
+  //       multisig: multisigKey,
+  //       proposal: proposalKey,
   //     })
-  //     .signers([wallet.payer])
   //     .rpc();
 
   //   await wait(1000);
+  //   // Only the changed role is staged; it takes over once admin1 signs accept_role.
   //   const fetchedAdminState: any = await program.account.adminState.fetch(adminKey);
   //   assert.ok(fetchedAdminState.admin1.toString() === wallet.publicKey.toString());
-  //   assert.ok(fetchedAdminState.admin2.toString() === admin2.toString());
+  //   assert.ok(fetchedAdminState.pendingAdmin1.toString() === admin1.publicKey.toString());
+  //   assert.ok(fetchedAdminState.pendingAdmin2 === null);
   // });
 
   // it("set fee", async () => {
-  //   const adminfee = 5;
-  //   const resolverfee = 1;
+  //   const fees = { adminFeeBps: 500, resolverFeeBps: 100, cancelFeeBps: 50, admin1ShareBps: 1500 };
+  //   // Fee changes must be announced at least two days ahead.
+  //   const effectiveAt = new anchor.BN(Math.floor(Date.now() / 1000) + 3 * 24 * 60 * 60);
+  //   const fetchedMultisig: any = await program.account.multisig.fetch(multisigKey);
+  //   const proposalKey = PublicKey.findProgramAddressSync(
+  //     [
+  //       Buffer.from(anchor.utils.bytes.utf8.encode(proposalSeed)),
+  //       fetchedMultisig.proposalCount.toArrayLike(Buffer, "le", 8),
+  //     ],
+  //     program.programId
+  //   )[0];
   //   await program.methods
-  //     .setFee(new anchor.BN(adminfee), new anchor.BN(resolverfee))
+  //     .createProposal({ setFee: { fees, effectiveAt } })
+  //     .accounts({
+  //       proposer: wallet.publicKey,
+  //       multisig: multisigKey,
+  //       proposal: proposalKey,
+  //       systemProgram: anchor.web3.SystemProgram.programId,
+  //     })
+  //     .rpc();
+
+  //   await program.methods
+  //     .setFee(fees.adminFeeBps, fees.resolverFeeBps, fees.cancelFeeBps, fees.admin1ShareBps, effectiveAt)
   //     .accounts({
-  //       admin1: wallet.publicKey.toString(),
   //       adminState: adminKey.toString(),
+  //       multisig: multisigKey,
+  //       proposal: proposalKey,
   //     })
-  //     .signers([wallet.payer])
   //     .rpc();
 
   //   await wait(500);
   //   const fetchedAdminState: any = await program.account.adminState.fetch(adminKey);
-  //   assert.ok(fetchedAdminState.adminFee.toNumber() === adminfee);
-  //   assert.ok(fetchedAdminState.resolverFee.toNumber() === resolverfee);
+  //   assert.ok(fetchedAdminState.pendingFees.adminFeeBps === fees.adminFeeBps);
+  //   assert.ok(fetchedAdminState.pendingFees.resolverFeeBps === fees.resolverFeeBps);
+  //   assert.ok(fetchedAdminState.feesEffectiveAt.eq(effectiveAt));
   // });
 
   // it("Initialize escrow", async () => {
   //   await program.methods
-  //     .initialize(randomSeed, [
-  //       new anchor.BN(50000),
-  //       new anchor.BN(150000),
-  //       new anchor.BN(200000),
-  //       new anchor.BN(50000),
-  //       new anchor.BN(50000),
-  //     ])
+  //     .initialize(
+  //       [
+  //         { amount: new anchor.BN(50000), specHash: Array(32).fill(0), dueTs: null },
+  //         { amount: new anchor.BN(150000), specHash: Array(32).fill(0), dueTs: null },
+  //         { amount: new anchor.BN(200000), specHash: Array(32).fill(0), dueTs: null },
+  //         { amount: new anchor.BN(50000), specHash: Array(32).fill(0), dueTs: null },
+  //         { amount: new anchor.BN(50000), specHash: Array(32).fill(0), dueTs: null },
+  //       ],
+  //       new anchor.BN(Math.floor(Date.now() / 1000) + 30 * 24 * 60 * 60)
+  //     )
   //     .accounts({
   //       initializer: initializer.publicKey,
   //       vault: vaultKey,
   //       adminState: adminKey,
   //       mint: mintA,
+  //       mintConfig: mintConfigKey,
+  //       mintStats: mintStatsKey,
   //       initializerDepositTokenAccount: initializerTokenAccountA,
   //       taker: taker.publicKey,
+  //       escrowCounter: escrowCounterKey,
   //       escrowState: escrowStateKey,
   //       systemProgram: anchor.web3.SystemProgram.programId,
   //       rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -284,7 +393,7 @@ describe("anchor-escrow", () => {
 
   //   // Check that the values in the escrow account match what we expect.
   //   assert.ok(fetchedEscrowState.initializerKey.equals(initializer.publicKey));
-  //   assert.ok(fetchedEscrowState.initializerAmount[0].toNumber() == 50000);
+  //   assert.ok(fetchedEscrowState.milestones[0].amount.toNumber() == 50000);
   //   assert.ok(fetchedEscrowState.mint.equals(mintA));
   //   console.log("fetchedEscrowState", fetchedEscrowState);
   //   console.log("fetchedAdminState", fetchedAdminState);
@@ -292,10 +401,44 @@ describe("anchor-escrow", () => {
 
   // it("Dispute", async () => {
   //   await program.methods
-  //     .dispute()
+  //     .dispute(new anchor.BN(0), 1)
   //     .accounts({
   //       disputor: taker.publicKey,
   //       escrowState: escrowStateKey,
+  //       adminState: adminKey,
+  //       mintStats: mintStatsKey,
+  //       disputeRecord: disputeRecordKey,
+  //       mintConfig: mintConfigKey,
+  //       mint: mintA,
+  //       disputorTokenAccount: takerTokenAccountA,
+  //       bondVault: bondVaultKey,
+  //       vaultAuthority: vaultAuthorityKey,
+  //       systemProgram: anchor.web3.SystemProgram.programId,
+  //       rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+  //       tokenProgram: TOKEN_PROGRAM_ID,
+  //     })
+  //     .signers([taker])
+  //     .rpc();
+
+  //   await wait(1000);
+
+  //   let fetchedEscrowState: any = await program.account.escrowState.fetch(escrowStateKey);
+  //   assert.ok(fetchedEscrowState.openDisputes === 1);
+  // });
+
+  // it("Withdraw the dispute", async () => {
+  //   await program.methods
+  //     .withdrawDispute(new anchor.BN(0))
+  //     .accounts({
+  //       opener: taker.publicKey,
+  //       openerTokenAccount: takerTokenAccountA,
+  //       escrowState: escrowStateKey,
+  //       adminState: adminKey,
+  //       mintStats: mintStatsKey,
+  //       disputeRecord: disputeRecordKey,
+  //       bondVault: bondVaultKey,
+  //       vaultAuthority: vaultAuthorityKey,
+  //       tokenProgram: TOKEN_PROGRAM_ID,
   //     })
   //     .signers([taker])
   //     .rpc();
@@ -303,15 +446,16 @@ describe("anchor-escrow", () => {
   //   await wait(1000);
 
   //   let fetchedEscrowState: any = await program.account.escrowState.fetch(escrowStateKey);
-  //   assert.ok(fetchedEscrowState.disputeStatus === true);
+  //   assert.ok(fetchedEscrowState.openDisputes === 0);
   // });
 
   // it("Solve the dispute", async () => {
   //   await program.methods
-  //     .resolve(new anchor.BN(1))
+  //     .resolve(new anchor.BN(1), 10000)
   //     .accounts({
   //       resolver: resolver.publicKey,
   //       takerTokenAccount: takerTokenAccountA,
+  //       initializerTokenAccount: initializerTokenAccountA,
   //       admin1TokenAccount: localWalletAccountA,
   //       admin2TokenAccount: admin2AccountA,
   //       resolverTokenAccount: resolverAccountA,
@@ -319,6 +463,10 @@ describe("anchor-escrow", () => {
   //       adminState: adminKey,
   //       vault: vaultKey,
   //       vaultAuthority: vaultAuthorityKey,
+  //       mintStats: mintStatsKey,
+  //       disputeRecord: disputeRecordKey,
+  //       bondVault: bondVaultKey,
+  //       opener: taker.publicKey,
   //       tokenProgram: TOKEN_PROGRAM_ID,
   //     })
   //     .signers([resolver])
@@ -339,10 +487,11 @@ describe("anchor-escrow", () => {
 
   // it("Solve the dispute", async () => {
   //   await program.methods
-  //     .resolve(new anchor.BN(1))
+  //     .resolve(new anchor.BN(1), 10000)
   //     .accounts({
   //       resolver: resolver.publicKey,
   //       takerTokenAccount: new PublicKey("FUXaFmc5xqKcSX1sdXVuEP5iYWjBSpYeWHxSRCwGxRmU"),
+  //       initializerTokenAccount: initializerTokenAccountA,
   //       admin1TokenAccount: localWalletAccountA,
   //       admin2TokenAccount: admin2AccountA,
   //       resolverTokenAccount: resolverAccountA,
@@ -350,6 +499,10 @@ describe("anchor-escrow", () => {
   //       adminState: adminKey,
   //       vault: vaultKey,
   //       vaultAuthority: vaultAuthorityKey,
+  //       mintStats: mintStatsKey,
+  //       disputeRecord: disputeRecordKey,
+  //       bondVault: bondVaultKey,
+  //       opener: taker.publicKey,
   //       tokenProgram: TOKEN_PROGRAM_ID,
   //     })
   //     .signers([resolver])
@@ -379,10 +532,11 @@ describe("anchor-escrow", () => {
   //     program.programId
   //   )[0];
   //   await program.methods
-  //     .resolve(new anchor.BN(0))
+  //     .resolve(new anchor.BN(0), 10000)
   //     .accounts({
   //       resolver: resolver.publicKey,
   //       takerTokenAccount: new PublicKey("FUXaFmc5xqKcSX1sdXVuEP5iYWjBSpYeWHxSRCwGxRmU"),
+  //       initializerTokenAccount: initializerTokenAccountA,
   //       admin1TokenAccount: localWalletAccountA,
   //       admin2TokenAccount: admin2AccountA,
   //       resolverTokenAccount: resolverAccountA,
@@ -390,6 +544,10 @@ describe("anchor-escrow", () => {
   //       adminState: adminKey,
   //       vault: vaultKey,
   //       vaultAuthority: vaultAuthorityKey,
+  //       mintStats: mintStatsKey,
+  //       disputeRecord: disputeRecordKey,
+  //       bondVault: bondVaultKey,
+  //       opener: taker.publicKey,
   //       tokenProgram: TOKEN_PROGRAM_ID,
   //     })
   //     .signers([resolver])
@@ -421,6 +579,7 @@ describe("anchor-escrow", () => {
   //       adminState: adminKey,
   //       vault: vaultKey,
   //       vaultAuthority: vaultAuthorityKey,
+  //       mintStats: mintStatsKey,
   //       tokenProgram: TOKEN_PROGRAM_ID,
   //     })
   //     .signers([initializer])
@@ -454,6 +613,7 @@ describe("anchor-escrow", () => {
   //       adminState: adminKey,
   //       vault: vaultKey,
   //       vaultAuthority: vaultAuthorityKey,
+  //       mintStats: mintStatsKey,
   //       tokenProgram: TOKEN_PROGRAM_ID,
   //     })
   //     .signers([taker])
@@ -470,12 +630,12 @@ describe("anchor-escrow", () => {
   //   console.log(Number(fetchedResolverTokenAccountA.amount));
   //   console.log(Number(fetchedAdmin1TokenAccountA.amount));
   //   console.log(Number(fetchedAdmin2TokenAccountA.amount));
-  //   console.log(fetchedEscrowState.initializerAmount);
+  //   console.log(fetchedEscrowState.milestones);
 
   //   assert.ok(1 === 1);
   // });
 
-  // it("Withdraw for resolve", async () => {
+  // it("Emergency withdraw", async () => {
   //   // Put back tokens into initializer token A account.
   //   await transfer(
   //     connection,
@@ -487,20 +647,26 @@ describe("anchor-escrow", () => {
   //   );
 
   //   await program.methods
-  //     .initialize(randomSeed2, [
-  //       new anchor.BN(50),
-  //       new anchor.BN(150),
-  //       new anchor.BN(200),
-  //       new anchor.BN(50),
-  //       new anchor.BN(50),
-  //     ])
+  //     .initialize(
+  //       [
+  //         { amount: new anchor.BN(50), specHash: Array(32).fill(0), dueTs: null },
+  //         { amount: new anchor.BN(150), specHash: Array(32).fill(0), dueTs: null },
+  //         { amount: new anchor.BN(200), specHash: Array(32).fill(0), dueTs: null },
+  //         { amount: new anchor.BN(50), specHash: Array(32).fill(0), dueTs: null },
+  //         { amount: new anchor.BN(50), specHash: Array(32).fill(0), dueTs: null },
+  //       ],
+  //       new anchor.BN(Math.floor(Date.now() / 1000) + 30 * 24 * 60 * 60)
+  //     )
   //     .accounts({
   //       initializer: initializer.publicKey,
   //       vault: vaultKey2,
   //       adminState: adminKey,
   //       mint: mintA,
+  //       mintConfig: mintConfigKey,
+  //       mintStats: mintStatsKey,
   //       initializerDepositTokenAccount: initializerTokenAccountA,
   //       taker: taker.publicKey,
+  //       escrowCounter: escrowCounterKey,
   //       escrowState: escrowStateKey2,
   //       systemProgram: anchor.web3.SystemProgram.programId,
   //       rent: anchor.web3.SYSVAR_RENT_PUBKEY,
@@ -512,19 +678,55 @@ describe("anchor-escrow", () => {
   //   await wait(1000);
 
   //   const originResolverTokenAccount = await getAccount(connection, localWalletAccountA);
-  //   // Cancel the escrow.
+  //   // Recovery needs a dispute or paused payouts.
+  //   await program.methods
+  //     .pause(2)
+  //     .accounts({
+  //       owner: wallet.publicKey,
+  //       multisig: multisigKey,
+  //       adminState: adminKey,
+  //     })
+  //     .rpc();
+
+  //   // The wallet is the only multisig owner, so its proposal is approved on creation.
+  //   const fetchedMultisig: any = await program.account.multisig.fetch(multisigKey);
+  //   const proposalKey = PublicKey.findProgramAddressSync(
+  //     [
+  //       Buffer.from(anchor.utils.bytes.utf8.encode(proposalSeed)),
+  //       fetchedMultisig.proposalCount.toArrayLike(Buffer, "le", 8),
+  //     ],
+  //     program.programId
+  //   )[0];
   //   await program.methods
-  //     .withdrawForResolve()
+  //     .createProposal({
+  //       emergencyWithdraw: {
+  //         escrowState: escrowStateKey2,
+  //         recipientTokenAccount: localWalletAccountA,
+  //         reasonCode: 1,
+  //       },
+  //     })
   //     .accounts({
-  //       resolver: wallet.publicKey,
-  //       resolverTokenAccount: localWalletAccountA,
+  //       proposer: wallet.publicKey,
+  //       multisig: multisigKey,
+  //       proposal: proposalKey,
+  //       systemProgram: anchor.web3.SystemProgram.programId,
+  //     })
+  //     .rpc();
+
+  //   await program.methods
+  //     .emergencyWithdraw(1)
+  //     .accounts({
+  //       initializer: initializer.publicKey,
+  //       recipientTokenAccount: localWalletAccountA,
   //       vault: vaultKey2,
-  //       vaultAuthority: vaultAuthorityKey,
+  //       vaultAuthority: vaultAuthorityKey2,
   //       adminState: adminKey,
   //       escrowState: escrowStateKey2,
+  //       multisig: multisigKey,
+  //       proposal: proposalKey,
+  //       mintStats: mintStatsKey,
   //       tokenProgram: TOKEN_PROGRAM_ID,
   //     })
-  //     .signers([wallet.payer])
   //     .rpc();
 
   //   await wait(1000);
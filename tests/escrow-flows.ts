@@ -0,0 +1,633 @@
+import * as anchor from "@project-serum/anchor";
+import { IDL } from "../target/types/anchor_escrow";
+import { PublicKey, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { TOKEN_PROGRAM_ID, createMint, createAccount, mintTo, getAccount } from "@solana/spl-token";
+import { assert } from "chai";
+
+function wait(milliseconds) {
+  return new Promise((resolve) => {
+    setTimeout(resolve, milliseconds);
+  });
+}
+
+// Runs the instructions that move funds end to end. Needs a fresh local validator:
+//   anchor test --provider.cluster localnet
+describe("escrow flows", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const connection = provider.connection;
+  const wallet = provider.wallet as anchor.Wallet;
+
+  const programId = new PublicKey("Ha9BXm9aGDU3Vyv3D2nvF2cMM2vMo1RqUiyEeL67smpP");
+  const program = new anchor.Program(IDL, programId, provider);
+  const systemProgram = anchor.web3.SystemProgram.programId;
+  const rent = anchor.web3.SYSVAR_RENT_PUBKEY;
+
+  // The wallet is admin1 and the only multisig owner, so its proposals execute right away.
+  const initializer = anchor.web3.Keypair.generate();
+  const taker = anchor.web3.Keypair.generate();
+  const admin2 = anchor.web3.Keypair.generate();
+  const resolver = anchor.web3.Keypair.generate();
+
+  // Mint fees set in before(); admin1 keeps the default 15% of the admin fee.
+  const adminFeeBps = 200;
+  const resolverFeeBps = 100;
+  const admin1ShareBps = 1500;
+  const disputeBond = 100;
+
+  const seed = (value: string) => Buffer.from(anchor.utils.bytes.utf8.encode(value));
+  const le = (value: number | anchor.BN, length: number) => new anchor.BN(value).toArrayLike(Buffer, "le", length);
+  const pda = (...seeds: Buffer[]) => PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+
+  const adminKey = pda(seed("state"), seed("admin"));
+  const multisigKey = pda(seed("multisig"));
+  const escrowCounterKey = pda(seed("counter"), initializer.publicKey.toBuffer());
+
+  let mint = null as PublicKey;
+  let mintConfigKey = null as PublicKey;
+  let mintStatsKey = null as PublicKey;
+  let initializerTokenAccount = null as PublicKey;
+  let takerTokenAccount = null as PublicKey;
+  let admin1TokenAccount = null as PublicKey;
+  let admin2TokenAccount = null as PublicKey;
+  let resolverTokenAccount = null as PublicKey;
+
+  const milestone = (amount: number) => ({ amount: new anchor.BN(amount), specHash: Array(32).fill(0), dueTs: null });
+
+  // Mirrors FeeSchedule::split: every leg rounds down and the recipient keeps the rest.
+  function split(amount: number, feeBps: number, resolverBps = 0) {
+    const adminFee = Math.floor((amount * feeBps) / 10000);
+    const admin1Fee = Math.floor((adminFee * admin1ShareBps) / 10000);
+    const resolverFee = Math.floor((amount * resolverBps) / 10000);
+    return {
+      admin1Fee,
+      admin2Fee: adminFee - admin1Fee,
+      resolverFee,
+      recipientAmount: amount - adminFee - resolverFee,
+    };
+  }
+
+  async function balance(tokenAccount: PublicKey) {
+    return Number((await getAccount(connection, tokenAccount)).amount);
+  }
+
+  async function balances() {
+    return {
+      initializer: await balance(initializerTokenAccount),
+      taker: await balance(takerTokenAccount),
+      admin1: await balance(admin1TokenAccount),
+      admin2: await balance(admin2TokenAccount),
+      resolver: await balance(resolverTokenAccount),
+    };
+  }
+
+  // Closed vaults count as empty.
+  async function vaultBalance(vault: PublicKey) {
+    const info = await connection.getAccountInfo(vault);
+    return info === null ? 0 : balance(vault);
+  }
+
+  async function mintStats() {
+    const stats: any = await program.account.mintStats.fetch(mintStatsKey);
+    return {
+      totalDeposited: stats.totalDeposited.toNumber(),
+      lockedAmount: stats.lockedAmount.toNumber(),
+      paidToTakers: stats.paidToTakers.toNumber(),
+      refunded: stats.refunded.toNumber(),
+      feesCollected: stats.feesCollected.toNumber(),
+      recovered: stats.recovered.toNumber(),
+      disputedAmount: stats.disputedAmount.toNumber(),
+      forfeitedBonds: stats.forfeitedBonds.toNumber(),
+    };
+  }
+
+  async function chainTime() {
+    return connection.getBlockTime(await connection.getSlot());
+  }
+
+  async function waitUntil(timestamp: number) {
+    while ((await chainTime()) < timestamp) {
+      await wait(500);
+    }
+  }
+
+  async function propose(action: any) {
+    const multisig: any = await program.account.multisig.fetch(multisigKey);
+    const proposalKey = pda(seed("proposal"), le(multisig.proposalCount, 8));
+    await program.methods
+      .createProposal(action)
+      .accounts({
+        proposer: wallet.publicKey,
+        multisig: multisigKey,
+        proposal: proposalKey,
+        systemProgram,
+      })
+      .rpc();
+    return proposalKey;
+  }
+
+  async function createEscrow(amounts: number[], expiresIn = 3600) {
+    const counter: any = await program.account.escrowCounter.fetch(escrowCounterKey);
+    const escrowState = pda(seed("state"), initializer.publicKey.toBuffer(), le(counter.nextId, 8));
+    const vault = pda(seed("vault"), escrowState.toBuffer());
+    const vaultAuthority = pda(seed("authority"), escrowState.toBuffer());
+    const expiresAt = (await chainTime()) + expiresIn;
+
+    await program.methods
+      .initialize(amounts.map(milestone), new anchor.BN(expiresAt))
+      .accounts({
+        initializer: initializer.publicKey,
+        taker: taker.publicKey,
+        adminState: adminKey,
+        mint,
+        mintConfig: mintConfigKey,
+        mintStats: mintStatsKey,
+        escrowCounter: escrowCounterKey,
+        initializerDepositTokenAccount: initializerTokenAccount,
+        escrowState,
+        vault,
+        vaultAuthority,
+        systemProgram,
+        rent,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([initializer])
+      .rpc();
+
+    return { escrowState, vault, vaultAuthority, expiresAt };
+  }
+
+  async function addMilestones(escrowState: PublicKey, vault: PublicKey, amounts: number[]) {
+    await program.methods
+      .addMilestones(amounts.map(milestone))
+      .accounts({
+        initializer: initializer.publicKey,
+        escrowState,
+        adminState: adminKey,
+        mintConfig: mintConfigKey,
+        mintStats: mintStatsKey,
+        initializerDepositTokenAccount: initializerTokenAccount,
+        vault,
+        systemProgram,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([initializer])
+      .rpc();
+  }
+
+  async function submitMilestone(escrowState: PublicKey, milestoneIdx: number) {
+    await program.methods
+      .submitMilestone(new anchor.BN(milestoneIdx), Array(32).fill(1))
+      .accounts({ taker: taker.publicKey, escrowState })
+      .signers([taker])
+      .rpc();
+  }
+
+  async function openDispute(
+    escrowState: PublicKey,
+    vaultAuthority: PublicKey,
+    milestoneIdx: number,
+    opener: anchor.web3.Keypair
+  ) {
+    const fetchedEscrowState: any = await program.account.escrowState.fetch(escrowState);
+    const seq = fetchedEscrowState.milestones[milestoneIdx].disputeCount;
+    const disputeRecord = pda(seed("dispute"), escrowState.toBuffer(), le(milestoneIdx, 8), le(seq, 2));
+    const bondVault = pda(seed("bond"), disputeRecord.toBuffer());
+    const openerTokenAccount = opener === taker ? takerTokenAccount : initializerTokenAccount;
+
+    await program.methods
+      .dispute(new anchor.BN(milestoneIdx), 1)
+      .accounts({
+        disputor: opener.publicKey,
+        escrowState,
+        adminState: adminKey,
+        mintStats: mintStatsKey,
+        disputeRecord,
+        mintConfig: mintConfigKey,
+        mint,
+        disputorTokenAccount: openerTokenAccount,
+        bondVault,
+        vaultAuthority,
+        systemProgram,
+        rent,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([opener])
+      .rpc();
+
+    return { disputeRecord, bondVault, openerTokenAccount };
+  }
+
+  async function resolve(
+    escrow: { escrowState: PublicKey; vault: PublicKey; vaultAuthority: PublicKey },
+    milestoneIdx: number,
+    takerShareBps: number,
+    dispute: { disputeRecord: PublicKey; bondVault: PublicKey },
+    opener: PublicKey
+  ) {
+    await program.methods
+      .resolve(new anchor.BN(milestoneIdx), takerShareBps)
+      .accounts({
+        resolver: resolver.publicKey,
+        takerTokenAccount,
+        initializerTokenAccount,
+        admin1TokenAccount,
+        admin2TokenAccount,
+        resolverTokenAccount,
+        escrowState: escrow.escrowState,
+        adminState: adminKey,
+        vault: escrow.vault,
+        vaultAuthority: escrow.vaultAuthority,
+        mintStats: mintStatsKey,
+        disputeRecord: dispute.disputeRecord,
+        bondVault: dispute.bondVault,
+        opener,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([resolver])
+      .rpc();
+  }
+
+  async function expectError(promise: Promise<unknown>, code: string) {
+    try {
+      await promise;
+    } catch (err) {
+      assert.equal(err.error.errorCode.code, code);
+      return;
+    }
+    assert.fail(`expected ${code}`);
+  }
+
+  before(async () => {
+    for (const keypair of [initializer, taker, resolver]) {
+      const signature = await connection.requestAirdrop(keypair.publicKey, 10 * LAMPORTS_PER_SOL);
+      await connection.confirmTransaction(signature, "confirmed");
+    }
+
+    mint = await createMint(connection, wallet.payer, wallet.publicKey, null, 0);
+    mintConfigKey = pda(seed("mint"), mint.toBuffer());
+    mintStatsKey = pda(seed("stats"), mint.toBuffer());
+    initializerTokenAccount = await createAccount(connection, wallet.payer, mint, initializer.publicKey);
+    takerTokenAccount = await createAccount(connection, wallet.payer, mint, taker.publicKey);
+    admin1TokenAccount = await createAccount(connection, wallet.payer, mint, wallet.publicKey);
+    admin2TokenAccount = await createAccount(connection, wallet.payer, mint, admin2.publicKey);
+    resolverTokenAccount = await createAccount(connection, wallet.payer, mint, resolver.publicKey);
+    await mintTo(connection, wallet.payer, mint, initializerTokenAccount, wallet.publicKey, 1_000_000);
+    await mintTo(connection, wallet.payer, mint, takerTokenAccount, wallet.publicKey, 10_000);
+
+    await program.methods
+      .initAdmin([wallet.publicKey], 1)
+      .accounts({
+        admin1: wallet.publicKey,
+        admin2: admin2.publicKey,
+        resolver: resolver.publicKey,
+        adminState: adminKey,
+        multisig: multisigKey,
+        systemProgram,
+        rent,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const params = {
+      enabled: true,
+      fees: { adminFeeBps, resolverFeeBps },
+      minAmount: new anchor.BN(1),
+      maxAmount: new anchor.BN(1_000_000),
+      disputeBond: new anchor.BN(disputeBond),
+    };
+    let proposal = await propose({ addMint: { mint, params } });
+    await program.methods
+      .addMint(params)
+      .accounts({
+        payer: wallet.publicKey,
+        mint,
+        mintConfig: mintConfigKey,
+        mintStats: mintStatsKey,
+        multisig: multisigKey,
+        proposal,
+        systemProgram,
+      })
+      .rpc();
+
+    // One-second review window so claim_after_timeout can run within the test.
+    proposal = await propose({ setReviewPeriod: { reviewPeriod: new anchor.BN(1) } });
+    await program.methods
+      .setReviewPeriod(new anchor.BN(1))
+      .accounts({ adminState: adminKey, multisig: multisigKey, proposal })
+      .rpc();
+
+    await program.methods
+      .initCounter()
+      .accounts({ initializer: initializer.publicKey, escrowCounter: escrowCounterKey, systemProgram })
+      .signers([initializer])
+      .rpc();
+  });
+
+  it("holds 30 milestones added in chunks before work starts", async () => {
+    const initializerBefore = await balance(initializerTokenAccount);
+    const statsBefore = await mintStats();
+
+    const { escrowState, vault } = await createEscrow(Array(12).fill(1000));
+    await addMilestones(escrowState, vault, Array(12).fill(1000));
+    await addMilestones(escrowState, vault, Array(6).fill(1000));
+    await expectError(addMilestones(escrowState, vault, [1000]), "InvalidMilestoneCount");
+
+    const fetchedEscrowState: any = await program.account.escrowState.fetch(escrowState);
+    const statsAfter = await mintStats();
+    assert.equal(fetchedEscrowState.milestones.length, 30);
+    assert.equal(await vaultBalance(vault), 30_000);
+    assert.equal(initializerBefore - (await balance(initializerTokenAccount)), 30_000);
+    assert.equal(statsAfter.totalDeposited - statsBefore.totalDeposited, 30_000);
+    assert.equal(statsAfter.lockedAmount - statsBefore.lockedAmount, 30_000);
+
+    // Once the taker has submitted work the milestone list is fixed.
+    const { escrowState: startedEscrowState, vault: startedVault } = await createEscrow([1000]);
+    await submitMilestone(startedEscrowState, 0);
+    await expectError(addMilestones(startedEscrowState, startedVault, [1000]), "EscrowStarted");
+  });
+
+  it("recovers a paused escrow to the initializer", async () => {
+    const { escrowState, vault, vaultAuthority } = await createEscrow([1000, 2000]);
+    const initializerBefore = await balance(initializerTokenAccount);
+    const statsBefore = await mintStats();
+
+    await program.methods
+      .pause(2)
+      .accounts({ owner: wallet.publicKey, multisig: multisigKey, adminState: adminKey })
+      .rpc();
+    try {
+      const proposal = await propose({
+        emergencyWithdraw: { escrowState, recipientTokenAccount: initializerTokenAccount, reasonCode: 7 },
+      });
+      await program.methods
+        .emergencyWithdraw(7)
+        .accounts({
+          initializer: initializer.publicKey,
+          vault,
+          vaultAuthority,
+          recipientTokenAccount: initializerTokenAccount,
+          escrowState,
+          adminState: adminKey,
+          multisig: multisigKey,
+          proposal,
+          mintStats: mintStatsKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    } finally {
+      const proposal = await propose({ setPauseFlags: { pauseFlags: 0 } });
+      await program.methods
+        .setPauseFlags(0)
+        .accounts({ adminState: adminKey, multisig: multisigKey, proposal })
+        .rpc();
+    }
+
+    const fetchedEscrowState: any = await program.account.escrowState.fetch(escrowState);
+    const statsAfter = await mintStats();
+    assert.equal(fetchedEscrowState.recoveryReason, 7);
+    assert.ok(fetchedEscrowState.milestones.every((m) => m.status.recovered !== undefined));
+    assert.equal((await balance(initializerTokenAccount)) - initializerBefore, 3000);
+    assert.equal(statsBefore.lockedAmount - statsAfter.lockedAmount, 3000);
+    assert.equal(statsAfter.recovered - statsBefore.recovered, 3000);
+    assert.equal(statsAfter.feesCollected, statsBefore.feesCollected);
+    assert.isNull(await connection.getAccountInfo(vault));
+  });
+
+  it("pays a submitted milestone out once the review window has passed", async () => {
+    const { escrowState, vault, vaultAuthority } = await createEscrow([1000]);
+    const before = await balances();
+    const statsBefore = await mintStats();
+
+    await submitMilestone(escrowState, 0);
+    const fetchedEscrowState: any = await program.account.escrowState.fetch(escrowState);
+    const submittedAt = fetchedEscrowState.milestones[0].submittedAt.toNumber();
+    await waitUntil(submittedAt + fetchedEscrowState.reviewPeriod.toNumber() + 1);
+
+    // Anyone can trigger the payout; the wallet does it here.
+    await program.methods
+      .claimAfterTimeout(new anchor.BN(0))
+      .accounts({
+        caller: wallet.publicKey,
+        takerTokenAccount,
+        admin1TokenAccount,
+        admin2TokenAccount,
+        escrowState,
+        adminState: adminKey,
+        vault,
+        vaultAuthority,
+        mintStats: mintStatsKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const fee = split(1000, adminFeeBps);
+    const after = await balances();
+    const statsAfter = await mintStats();
+    assert.equal(after.taker - before.taker, fee.recipientAmount);
+    assert.equal(after.admin1 - before.admin1, fee.admin1Fee);
+    assert.equal(after.admin2 - before.admin2, fee.admin2Fee);
+    assert.equal(statsBefore.lockedAmount - statsAfter.lockedAmount, 1000);
+    assert.equal(statsAfter.paidToTakers - statsBefore.paidToTakers, fee.recipientAmount);
+    assert.equal(statsAfter.feesCollected - statsBefore.feesCollected, fee.admin1Fee + fee.admin2Fee);
+    assert.equal(await vaultBalance(vault), 0);
+  });
+
+  it("returns unsubmitted milestones to the initializer after expiry", async () => {
+    const before = await balances();
+    const statsBefore = await mintStats();
+    const { escrowState, vault, vaultAuthority, expiresAt } = await createEscrow([1000, 500], 5);
+
+    const reclaim = () =>
+      program.methods
+        .reclaimExpired()
+        .accounts({
+          initializer: initializer.publicKey,
+          initializerDepositTokenAccount: initializerTokenAccount,
+          admin1TokenAccount,
+          admin2TokenAccount,
+          escrowState,
+          adminState: adminKey,
+          vault,
+          vaultAuthority,
+          mintStats: mintStatsKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([initializer])
+        .rpc();
+    await expectError(reclaim(), "EscrowNotExpired");
+    await waitUntil(expiresAt + 1);
+    await reclaim();
+
+    const fee = split(1500, adminFeeBps);
+    const after = await balances();
+    const statsAfter = await mintStats();
+    assert.equal(before.initializer - after.initializer, fee.admin1Fee + fee.admin2Fee);
+    assert.equal(after.admin1 - before.admin1, fee.admin1Fee);
+    assert.equal(after.admin2 - before.admin2, fee.admin2Fee);
+    assert.equal(statsAfter.totalDeposited - statsBefore.totalDeposited, 1500);
+    assert.equal(statsAfter.lockedAmount, statsBefore.lockedAmount);
+    assert.equal(statsAfter.refunded - statsBefore.refunded, fee.recipientAmount);
+    assert.equal(statsAfter.feesCollected - statsBefore.feesCollected, fee.admin1Fee + fee.admin2Fee);
+    assert.isNull(await connection.getAccountInfo(vault));
+  });
+
+  it("cancels an escrow with both signatures and closes it", async () => {
+    const before = await balances();
+    const statsBefore = await mintStats();
+    const { escrowState, vault, vaultAuthority } = await createEscrow([1000, 2000]);
+
+    await program.methods
+      .cancel()
+      .accounts({
+        initializer: initializer.publicKey,
+        taker: taker.publicKey,
+        initializerDepositTokenAccount: initializerTokenAccount,
+        admin1TokenAccount,
+        admin2TokenAccount,
+        escrowState,
+        adminState: adminKey,
+        vault,
+        vaultAuthority,
+        mintStats: mintStatsKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([initializer, taker])
+      .rpc();
+
+    // No cancel fee is configured, so the whole deposit comes back.
+    const after = await balances();
+    const statsAfter = await mintStats();
+    assert.equal(after.initializer, before.initializer);
+    assert.equal(after.admin1, before.admin1);
+    assert.equal(after.admin2, before.admin2);
+    assert.equal(statsAfter.totalDeposited - statsBefore.totalDeposited, 3000);
+    assert.equal(statsAfter.lockedAmount, statsBefore.lockedAmount);
+    assert.equal(statsAfter.refunded - statsBefore.refunded, 3000);
+    assert.equal(statsAfter.feesCollected, statsBefore.feesCollected);
+    assert.isNull(await connection.getAccountInfo(vault));
+    assert.isNull(await connection.getAccountInfo(escrowState));
+  });
+
+  it("splits a disputed milestone and returns the bond to an opener who gets half", async () => {
+    const escrow = await createEscrow([1000]);
+    const before = await balances();
+    const statsBefore = await mintStats();
+
+    const dispute = await openDispute(escrow.escrowState, escrow.vaultAuthority, 0, taker);
+    assert.equal(await balance(dispute.bondVault), disputeBond);
+    assert.equal((await mintStats()).disputedAmount - statsBefore.disputedAmount, 1000);
+    await resolve(escrow, 0, 5000, dispute, taker.publicKey);
+
+    const fee = split(1000, adminFeeBps, resolverFeeBps);
+    const takerAmount = Math.floor((fee.recipientAmount * 5000) / 10000);
+    const after = await balances();
+    const statsAfter = await mintStats();
+    assert.equal(after.taker - before.taker, takerAmount);
+    assert.equal(after.initializer - before.initializer, fee.recipientAmount - takerAmount);
+    assert.equal(after.admin1 - before.admin1, fee.admin1Fee);
+    assert.equal(after.admin2 - before.admin2, fee.admin2Fee);
+    assert.equal(after.resolver - before.resolver, fee.resolverFee);
+    assert.equal(statsBefore.lockedAmount - statsAfter.lockedAmount, 1000);
+    assert.equal(statsAfter.disputedAmount, statsBefore.disputedAmount);
+    assert.equal(statsAfter.paidToTakers - statsBefore.paidToTakers, takerAmount);
+    assert.equal(statsAfter.refunded - statsBefore.refunded, fee.recipientAmount - takerAmount);
+    assert.equal(
+      statsAfter.feesCollected - statsBefore.feesCollected,
+      fee.admin1Fee + fee.admin2Fee + fee.resolverFee
+    );
+    assert.equal(statsAfter.forfeitedBonds, statsBefore.forfeitedBonds);
+    assert.equal(await vaultBalance(escrow.vault), 0);
+    assert.isNull(await connection.getAccountInfo(dispute.bondVault));
+
+    const fetchedDisputeRecord: any = await program.account.disputeRecord.fetch(dispute.disputeRecord);
+    assert.isNotNull(fetchedDisputeRecord.resolvedAt);
+    assert.equal(fetchedDisputeRecord.takerShareBps, 5000);
+  });
+
+  it("forfeits the bond to the resolver when the opener loses the ruling", async () => {
+    const escrow = await createEscrow([1000]);
+    const before = await balances();
+    const statsBefore = await mintStats();
+
+    const dispute = await openDispute(escrow.escrowState, escrow.vaultAuthority, 0, initializer);
+    await resolve(escrow, 0, 10000, dispute, initializer.publicKey);
+
+    const fee = split(1000, adminFeeBps, resolverFeeBps);
+    const after = await balances();
+    const statsAfter = await mintStats();
+    assert.equal(after.taker - before.taker, fee.recipientAmount);
+    assert.equal(before.initializer - after.initializer, disputeBond);
+    assert.equal(after.admin1 - before.admin1, fee.admin1Fee);
+    assert.equal(after.admin2 - before.admin2, fee.admin2Fee);
+    assert.equal(after.resolver - before.resolver, fee.resolverFee + disputeBond);
+    assert.equal(statsBefore.lockedAmount - statsAfter.lockedAmount, 1000);
+    assert.equal(statsAfter.paidToTakers - statsBefore.paidToTakers, fee.recipientAmount);
+    assert.equal(statsAfter.refunded, statsBefore.refunded);
+    assert.equal(statsAfter.forfeitedBonds - statsBefore.forfeitedBonds, disputeBond);
+    assert.equal(await vaultBalance(escrow.vault), 0);
+    assert.isNull(await connection.getAccountInfo(dispute.bondVault));
+  });
+
+  it("withdraws a dispute, refunds the bond and lets the milestone be approved", async () => {
+    const { escrowState, vault, vaultAuthority } = await createEscrow([1000]);
+    await submitMilestone(escrowState, 0);
+    const submittedAt = ((await program.account.escrowState.fetch(escrowState)) as any).milestones[0].submittedAt;
+    const before = await balances();
+    const statsBefore = await mintStats();
+
+    const dispute = await openDispute(escrowState, vaultAuthority, 0, taker);
+    await program.methods
+      .withdrawDispute(new anchor.BN(0))
+      .accounts({
+        opener: taker.publicKey,
+        openerTokenAccount: takerTokenAccount,
+        escrowState,
+        adminState: adminKey,
+        mintStats: mintStatsKey,
+        disputeRecord: dispute.disputeRecord,
+        bondVault: dispute.bondVault,
+        vaultAuthority,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([taker])
+      .rpc();
+
+    const fetchedEscrowState: any = await program.account.escrowState.fetch(escrowState);
+    const fetchedDisputeRecord: any = await program.account.disputeRecord.fetch(dispute.disputeRecord);
+    assert.ok(fetchedEscrowState.milestones[0].status.submitted !== undefined);
+    assert.ok(fetchedEscrowState.milestones[0].submittedAt.eq(submittedAt));
+    assert.equal(fetchedEscrowState.openDisputes, 0);
+    assert.isNotNull(fetchedDisputeRecord.withdrawnAt);
+    assert.equal(await balance(takerTokenAccount), before.taker);
+    assert.equal((await mintStats()).disputedAmount, statsBefore.disputedAmount);
+    assert.isNull(await connection.getAccountInfo(dispute.bondVault));
+
+    await program.methods
+      .approve(new anchor.BN(0))
+      .accounts({
+        initializer: initializer.publicKey,
+        takerTokenAccount,
+        admin1TokenAccount,
+        admin2TokenAccount,
+        escrowState,
+        adminState: adminKey,
+        vault,
+        vaultAuthority,
+        mintStats: mintStatsKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([initializer])
+      .rpc();
+
+    const fee = split(1000, adminFeeBps);
+    const after = await balances();
+    const statsAfter = await mintStats();
+    assert.equal(after.taker - before.taker, fee.recipientAmount);
+    assert.equal(after.admin1 - before.admin1, fee.admin1Fee);
+    assert.equal(after.admin2 - before.admin2, fee.admin2Fee);
+    assert.equal(statsBefore.lockedAmount - statsAfter.lockedAmount, 1000);
+    assert.equal(statsAfter.paidToTakers - statsBefore.paidToTakers, fee.recipientAmount);
+    assert.equal(await vaultBalance(vault), 0);
+  });
+});